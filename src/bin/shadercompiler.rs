@@ -0,0 +1,401 @@
+#![allow(non_snake_case)]
+//! Compiles every shader under a directory ahead of time.
+//!
+//! Usage: `shadercompiler <input dir> <output dir> [-I <include dir>]... [-D <NAME>[=<VALUE>]]... [-O] [-g] [--rust <file>]`
+//!
+//! `-O` optimizes for performance and `-g` keeps debug info in the SPIR-V. `--rust` writes `#[repr(C)]` structs
+//! for every uniform and storage block to `<file>`, see `codegen`.
+//!
+//! Each file with a stage suffix (`water.vert`, `water.frag.hlsl`, ...) is compiled to `<name>.<stage>.spv` at
+//! the same relative path in the output directory, and `manifest.json` there describes the reflection data of
+//! all of them. Exits with a non-zero status if any shader fails to compile or reflect.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use VkRender::render::shader::{
+    codegen,
+    diagnostics::{self, Diagnostic, Severity},
+    loader::{detectFormat, ShaderFileFormat},
+    reflection::{self, reflectEntryPoint, ReflectionData, ShaderVariable},
+    OptimizationLevel, ShaderCompileInfo, ShaderCompileOptions, ShaderDefines, ShaderStage,
+};
+
+struct Arguments {
+    input: PathBuf,
+    output: PathBuf,
+    includePaths: Vec<PathBuf>,
+    defines: ShaderDefines,
+    options: ShaderCompileOptions,
+    rust: Option<PathBuf>,
+}
+
+fn parseArguments() -> Result<Arguments, String> {
+    let mut positional = Vec::new();
+    let mut includePaths = Vec::new();
+    let mut defines = ShaderDefines::new();
+    let mut options = ShaderCompileOptions::default();
+    let mut rust = None;
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-I" => includePaths.push(PathBuf::from(args.next().ok_or("-I needs a directory")?)),
+            "-D" => {
+                let define = args.next().ok_or("-D needs a macro name")?;
+
+                match define.find('=') {
+                    Some(index) => defines.define(&define[..index], Some(&define[index + 1..])),
+                    None => defines.define(&define, None),
+                };
+            }
+            "-O" => options.optimization = OptimizationLevel::Performance,
+            "-g" => options.debugInfo = true,
+            "--rust" => rust = Some(PathBuf::from(args.next().ok_or("--rust needs a file")?)),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+
+    match <[PathBuf; 2]>::try_from(positional) {
+        Ok([input, output]) => Ok(Arguments {
+            input,
+            output,
+            includePaths,
+            defines,
+            options,
+            rust,
+        }),
+        Err(_) => Err("usage: shadercompiler <input dir> <output dir> [-I <include dir>]... [-D <NAME>[=<VALUE>]]... [-O] [-g] [--rust <file>]".to_owned()),
+    }
+}
+
+/// Every file under `directory`, in a stable order.
+fn collectFiles(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collectFiles(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// A compiled shader, for the manifest.
+struct CompiledShader {
+    source: PathBuf,
+    output: PathBuf,
+    stage: ShaderStage,
+    dependencies: Vec<PathBuf>,
+    reflection: ReflectionData,
+}
+
+/// A shader file waiting to be compiled.
+struct Job {
+    path: PathBuf,
+    relative: PathBuf,
+    name: String,
+    output: PathBuf,
+    stage: ShaderStage,
+    compileInfo: ShaderCompileInfo,
+}
+
+fn prepare(arguments: &Arguments, path: &Path, stage: ShaderStage) -> Result<Job, Box<dyn Error>> {
+    let relative = path.strip_prefix(&arguments.input)?;
+
+    // `water.frag.hlsl` and `water.frag` both become `water.frag.spv`.
+    let name = relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+        .unwrap_or_default()
+        .to_owned();
+
+    let output = relative.with_file_name(format!("{}.{}.spv", name, stage.extension()));
+
+    let compileInfo = arguments
+        .includePaths
+        .iter()
+        .fold(ShaderCompileInfo::fromFile(path, stage)?, |info, includePath| {
+            info.includePath(includePath)
+        })
+        .defines(&arguments.defines)
+        .options(arguments.options.clone());
+
+    Ok(Job {
+        path: path.to_owned(),
+        relative: relative.to_owned(),
+        name,
+        output,
+        stage,
+        compileInfo,
+    })
+}
+
+/// Reflects and writes out the SPIR-V of a job.
+fn finish(arguments: &Arguments, job: Job, spirv: Vec<u8>, dependencies: Vec<PathBuf>) -> Result<CompiledShader, Box<dyn Error>> {
+    reflection::validateEntryPoint(&job.name, &spirv, job.compileInfo.entryPoint(), job.stage)?;
+
+    let reflection = reflectEntryPoint(&spirv, job.compileInfo.entryPoint(), job.stage)?;
+
+    let outputPath = arguments.output.join(&job.output);
+
+    if let Some(directory) = outputPath.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    fs::write(&outputPath, &spirv)?;
+
+    Ok(CompiledShader {
+        source: job.relative,
+        output: job.output,
+        stage: job.stage,
+        dependencies,
+        reflection,
+    })
+}
+
+fn jsonString(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    escaped.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if (character as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", character as u32)),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped.push('"');
+
+    escaped
+}
+
+fn jsonPath(path: &Path) -> String {
+    jsonString(&path.to_string_lossy().replace('\\', "/"))
+}
+
+fn jsonArray<I: IntoIterator<Item = String>>(items: I) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(", "))
+}
+
+/// Interface variables ordered by location, since the reflection data keeps them in a map.
+fn jsonVariables<'a, I: Iterator<Item = &'a ShaderVariable>>(variables: I) -> String {
+    let mut variables: Vec<_> = variables.collect();
+    variables.sort_by_key(|variable| variable.location());
+
+    jsonArray(variables.into_iter().map(|variable| {
+        format!(
+            "{{\"name\": {}, \"location\": {}, \"type\": {}}}",
+            jsonString(variable.name()),
+            variable.location(),
+            jsonString(&format!("{:?}", variable.dataType()))
+        )
+    }))
+}
+
+fn jsonShader(shader: &CompiledShader) -> String {
+    let reflection = &shader.reflection;
+
+    let bindings = jsonArray(reflection.bindings().iter().map(|binding| {
+        format!(
+            "{{\"name\": {}, \"set\": {}, \"binding\": {}, \"type\": {}, \"count\": {}, \"size\": {}}}",
+            jsonString(binding.name()),
+            binding.set(),
+            binding.binding(),
+            jsonString(&format!("{:?}", binding.descriptorType())),
+            binding.count(),
+            binding
+                .block()
+                .map_or("null".to_owned(), |block| block.size().to_string())
+        )
+    }));
+
+    let pushConstants = reflection.pushConstants().map_or("null".to_owned(), |block| {
+        let (offset, size) = block.range();
+
+        format!(
+            "{{\"name\": {}, \"offset\": {}, \"size\": {}}}",
+            jsonString(block.name()),
+            offset,
+            size
+        )
+    });
+
+    let specializationConstants = jsonArray(reflection.specializationConstants().iter().map(|constant| {
+        format!(
+            "{{\"name\": {}, \"id\": {}, \"type\": {}}}",
+            jsonString(constant.name()),
+            constant.id(),
+            jsonString(&format!("{:?}", constant.constantType()))
+        )
+    }));
+
+    let workgroupSize = reflection
+        .workgroupSize()
+        .map_or("null".to_owned(), |size| jsonArray(size.iter().map(u32::to_string)));
+
+    [
+        format!("\"source\": {}", jsonPath(&shader.source)),
+        format!("\"output\": {}", jsonPath(&shader.output)),
+        format!("\"stage\": {}", jsonString(&format!("{:?}", shader.stage))),
+        format!(
+            "\"dependencies\": {}",
+            jsonArray(shader.dependencies.iter().map(|path| jsonPath(path)))
+        ),
+        format!("\"inputs\": {}", jsonVariables(reflection.inputs().values())),
+        format!("\"outputs\": {}", jsonVariables(reflection.outputs().values())),
+        format!("\"bindings\": {}", bindings),
+        format!("\"pushConstants\": {}", pushConstants),
+        format!("\"specializationConstants\": {}", specializationConstants),
+        format!("\"workgroupSize\": {}", workgroupSize),
+    ]
+    .iter()
+    .map(|field| format!("      {}", field))
+    .collect::<Vec<_>>()
+    .join(",\n")
+}
+
+fn writeManifest(output: &Path, shaders: &[CompiledShader]) -> std::io::Result<()> {
+    let shaders = shaders
+        .iter()
+        .map(|shader| format!("    {{\n{}\n    }}", jsonShader(shader)))
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    fs::write(
+        output.join("manifest.json"),
+        format!("{{\n  \"shaders\": [\n{}\n  ]\n}}\n", shaders),
+    )
+}
+
+fn main() {
+    let arguments = match parseArguments() {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+
+    // Errors come back from `compileAll`, only warnings need to be picked up here.
+    diagnostics::setDiagnosticSink(Some(Box::new(|_: &str, diagnostics: &[Diagnostic]| {
+        for diagnostic in diagnostics {
+            if diagnostic.severity != Severity::Error {
+                eprintln!("{}", diagnostic);
+            }
+        }
+    })));
+
+    let mut files = Vec::new();
+
+    if let Err(err) = collectFiles(&arguments.input, &mut files) {
+        eprintln!("Failed to read {:?}: {}", arguments.input, err);
+        process::exit(1);
+    }
+
+    let mut jobs = Vec::new();
+    let mut failures = 0;
+
+    for path in files {
+        let stage = match detectFormat(&path) {
+            Ok((ShaderFileFormat::Spirv, _)) | Err(_) => continue,
+            Ok((_, Some(stage))) => stage,
+            Ok((_, None)) => {
+                eprintln!("Skipping {:?}: no stage suffix such as .vert or .frag", path);
+                continue;
+            }
+        };
+
+        match prepare(&arguments, &path, stage) {
+            Ok(job) => jobs.push(job),
+            Err(err) => {
+                eprintln!("Failed to read {:?}: {}", path, err);
+                failures += 1;
+            }
+        }
+    }
+
+    let stages: Vec<_> = jobs
+        .iter()
+        .map(|job| (job.name.to_owned(), job.compileInfo.clone(), job.stage))
+        .collect();
+
+    let mut compiled = Vec::new();
+
+    for (job, result) in jobs.into_iter().zip(ShaderCompileInfo::compileAll(&stages)) {
+        let path = job.path.clone();
+
+        let shader = result
+            .map_err(|err| -> Box<dyn Error> { err })
+            .and_then(|(spirv, dependencies)| finish(&arguments, job, spirv, dependencies));
+
+        match shader {
+            Ok(shader) => {
+                println!("Compiled {:?} -> {:?}", path, shader.output);
+                compiled.push(shader);
+            }
+            Err(err) => {
+                eprintln!("Failed to compile {:?}: {}", path, err);
+                failures += 1;
+            }
+        }
+    }
+
+    if let Err(err) = fs::create_dir_all(&arguments.output).and_then(|_| writeManifest(&arguments.output, &compiled)) {
+        eprintln!("Failed to write the manifest to {:?}: {}", arguments.output, err);
+        process::exit(1);
+    }
+
+    if let Some(path) = &arguments.rust {
+        let mut generated = BTreeMap::new();
+
+        let structs = compiled
+            .iter()
+            .map(|shader| codegen::generateBlocks(&shader.reflection, &mut generated))
+            .collect::<Result<Vec<_>, _>>();
+
+        let structs: Vec<_> = match structs {
+            Ok(structs) => structs.into_iter().filter(|structs| !structs.is_empty()).collect(),
+            Err(err) => {
+                eprintln!("Failed to generate the block structs: {}", err);
+                process::exit(1);
+            }
+        };
+
+        let source = format!(
+            "// Generated by shadercompiler from the reflected block layouts, do not edit.\n\n{}",
+            structs.join("\n")
+        );
+
+        if let Err(err) = fs::write(path, source) {
+            eprintln!("Failed to write the block structs to {:?}: {}", path, err);
+            process::exit(1);
+        }
+    }
+
+    if failures > 0 {
+        eprintln!("{} of {} shaders failed", failures, failures + compiled.len());
+        process::exit(1);
+    }
+}
@@ -0,0 +1,10 @@
+#![allow(non_snake_case)]
+pub mod render;
+
+extern crate ash;
+extern crate ash_window;
+
+#[macro_use]
+extern crate lazy_static;
+
+pub use render::*;
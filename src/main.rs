@@ -1,19 +1,11 @@
 #![allow(non_snake_case)]
-mod render;
-
-extern crate ash;
-extern crate ash_window;
-
-#[macro_use]
-extern crate lazy_static;
-
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
-use render::*;
+use VkRender::render::*;
 
 pub fn setupLogging() {
     let colors_line = fern::colors::ColoredLevelConfig::new()
@@ -4,3 +4,8 @@ pub struct CommandBuffer {
     inner: vk::CommandBuffer,
     pool: vk::CommandPool,
 }
+impl CommandBuffer {
+    pub fn raw(&self) -> vk::CommandBuffer {
+        self.inner
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, error::Error, ffi::CStr, ptr::swap, sync::{Arc, Mutex}};
+use std::{collections::HashSet, error::Error, ffi::{c_void, CStr}, ptr::swap, sync::{Arc, Mutex}};
 
 use ash::{
     extensions::khr::{Surface, Swapchain},
@@ -8,7 +8,11 @@ use ash::{
 };
 use gpu_allocator::VulkanAllocator;
 
-use super::graphics;
+use super::{
+    dynamicrendering::{DynamicRendering, PhysicalDeviceDynamicRenderingFeaturesKHR},
+    graphics,
+    shader::registry::ShaderRegistry,
+};
 
 pub struct PhysicalDeviceInfo {
     device: vk::PhysicalDevice,
@@ -16,6 +20,8 @@ pub struct PhysicalDeviceInfo {
     presentQueue: Option<usize>,
     swapchainSupportDetails: SwapchainSupportDetails,
     properties: ash::vk::PhysicalDeviceProperties,
+    features: vk::PhysicalDeviceFeatures,
+    dynamicRendering: bool,
     suitable: bool,
 }
 impl PhysicalDeviceInfo {
@@ -25,37 +31,33 @@ impl PhysicalDeviceInfo {
         surface: &vk::SurfaceKHR,
         surfaceLoader: &Surface,
         requiredExtensions: &[&CStr],
+        properties2: bool,
     ) -> Result<Self, Box<dyn Error>> {
         let graphicsQueue = Self::getGraphicsQueue(&device, instance);
         let presentQueue = Self::getPresentQueue(&device, instance, surface, surfaceLoader);
 
         let properties = unsafe { instance.get_physical_device_properties(device) };
+        let features = unsafe { instance.get_physical_device_features(device) };
         let discreteGPU = properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU;
 
         let extensions = unsafe { instance.enumerate_device_extension_properties(device)? };
 
-        let extensionsSupport = requiredExtensions.iter().all(|x| {
-            extensions
-                .iter()
-                .find(|&&y| {
-                    let weirdness = unsafe { &*{ x.to_bytes() as *const [u8] as *const [i8] } };
-
-                    let other = &y.extension_name[..weirdness.len()];
+        let extensionsSupport = Self::supportsExtensions(&extensions, requiredExtensions);
 
-                    other.eq(weirdness)
-                })
-                .is_some()
-        });
+        // The extensions dynamic rendering depends on need VK_KHR_get_physical_device_properties2 on the instance.
+        let dynamicRendering =
+            properties2 && Self::supportsExtensions(&extensions, &DynamicRendering::requiredExtensions());
 
         let swapchainSupportDetails =
             SwapchainSupportDetails::create(&device, surfaceLoader, surface)?;
 
         log::info!(
-            "\ngraphicsQueue: {}\npresentQueue: {}\ndiscreteGPU: {}\nextensionSupport : {}",
+            "\ngraphicsQueue: {}\npresentQueue: {}\ndiscreteGPU: {}\nextensionSupport : {}\ndynamicRendering: {}",
             graphicsQueue.is_some(),
             presentQueue.is_some(),
             discreteGPU,
-            extensionsSupport
+            extensionsSupport,
+            dynamicRendering
         );
 
         let suitable = graphicsQueue.is_some() && presentQueue.is_some() && extensionsSupport;
@@ -63,6 +65,8 @@ impl PhysicalDeviceInfo {
         Ok(Self {
             device,
             properties,
+            features,
+            dynamicRendering,
             graphicsQueue,
             presentQueue,
             suitable,
@@ -72,6 +76,20 @@ impl PhysicalDeviceInfo {
     pub fn isSuitable(&self) -> bool {
         self.suitable
     }
+    fn supportsExtensions(extensions: &[vk::ExtensionProperties], names: &[&CStr]) -> bool {
+        names.iter().all(|x| {
+            extensions
+                .iter()
+                .find(|&&y| {
+                    let weirdness = unsafe { &*{ x.to_bytes() as *const [u8] as *const [i8] } };
+
+                    let other = &y.extension_name[..weirdness.len()];
+
+                    other.eq(weirdness) && y.extension_name[weirdness.len()] == 0
+                })
+                .is_some()
+        })
+    }
     fn getGraphicsQueue(device: &vk::PhysicalDevice, instance: &ash::Instance) -> Option<usize> {
         let props = unsafe { instance.get_physical_device_queue_family_properties(*device) };
         props
@@ -115,6 +133,8 @@ impl PhysicalDeviceInfo {
             inner: self.device,
             swapchainSupportDetails: self.swapchainSupportDetails,
             properties: self.properties,
+            features: self.features,
+            dynamicRendering: self.dynamicRendering,
         }
     }
 }
@@ -159,6 +179,8 @@ pub struct PhysicalDevice {
     presentQueueIx: usize,
     swapchainSupportDetails: SwapchainSupportDetails,
     properties: ash::vk::PhysicalDeviceProperties,
+    features: vk::PhysicalDeviceFeatures,
+    dynamicRendering: bool,
 }
 pub struct GPUProperties<'a> {
     name: &'a str,
@@ -174,6 +196,7 @@ impl PhysicalDevice {
         instance: &Instance,
         surface: vk::SurfaceKHR,
         surfaceLoader: &Surface,
+        properties2: bool,
     ) -> Result<PhysicalDevice, Box<dyn Error>> {
         let devices = unsafe { instance.enumerate_physical_devices()? };
         use super::device::PhysicalDeviceInfo;
@@ -189,6 +212,7 @@ impl PhysicalDevice {
                     &surface,
                     &surfaceLoader,
                     &requiredExtensions,
+                    properties2,
                 )
                 .unwrap()
             })
@@ -231,15 +255,35 @@ impl PhysicalDevice {
     pub fn rawDevice(&self) -> vk::PhysicalDevice {
         self.inner
     }
+    pub fn features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.features
+    }
+    pub fn limits(&self) -> &vk::PhysicalDeviceLimits {
+        &self.properties.limits
+    }
+    pub fn supportsDynamicRendering(&self) -> bool {
+        self.dynamicRendering
+    }
 }
 
 impl Drop for PhysicalDevice {
     fn drop(&mut self) {}
 }
 
+/// `VulkanAllocator` is `!Send` only because its memory blocks hold the raw pointers of mapped memory and boxed
+/// sub-allocators without a `Send` bound. Neither is tied to the thread that created it: the mappings stay valid
+/// for the device's lifetime, and the sub-allocators are plain bookkeeping. The `Mutex` in `Device` guarantees
+/// that only one thread touches the allocator at a time, which is all it needs.
+struct SendAllocator(VulkanAllocator);
+unsafe impl Send for SendAllocator {}
+
 pub struct Device {
     inner: ash::Device,
-    allocator: Mutex<VulkanAllocator>,
+    allocator: Mutex<SendAllocator>,
+    enabledFeatures: vk::PhysicalDeviceFeatures,
+    limits: vk::PhysicalDeviceLimits,
+    dynamicRendering: Option<DynamicRendering>,
+    shaderRegistry: ShaderRegistry,
 }
 impl Device {
     pub(super) fn create(
@@ -262,15 +306,48 @@ impl Device {
             })
             .collect();
 
-        let extensions = [Swapchain::name().as_ptr()];
+        let mut extensions = vec![Swapchain::name().as_ptr()];
 
-        let deviceCreateInfo = vk::DeviceCreateInfo::builder()
+        if physicalDevice.supportsDynamicRendering() {
+            extensions.extend(DynamicRendering::requiredExtensions().iter().map(|name| name.as_ptr()));
+        }
+
+        let mut dynamicRenderingFeatures = PhysicalDeviceDynamicRenderingFeaturesKHR::default();
+
+        // Optional features, enabled whenever the GPU has them; pipelines check for them at creation.
+        let supported = physicalDevice.features();
+        let enabledFeatures = vk::PhysicalDeviceFeatures::builder()
+            .fill_mode_non_solid(supported.fill_mode_non_solid == vk::TRUE)
+            .depth_clamp(supported.depth_clamp == vk::TRUE)
+            .depth_bias_clamp(supported.depth_bias_clamp == vk::TRUE)
+            .wide_lines(supported.wide_lines == vk::TRUE)
+            .tessellation_shader(supported.tessellation_shader == vk::TRUE)
+            .geometry_shader(supported.geometry_shader == vk::TRUE)
+            .shader_float64(supported.shader_float64 == vk::TRUE)
+            .shader_clip_distance(supported.shader_clip_distance == vk::TRUE)
+            .shader_cull_distance(supported.shader_cull_distance == vk::TRUE)
+            .shader_storage_image_extended_formats(supported.shader_storage_image_extended_formats == vk::TRUE)
+            .build();
+
+        let mut deviceCreateInfo = vk::DeviceCreateInfo::builder()
             .enabled_extension_names(&extensions)
-            .queue_create_infos(&queueCreateInfos);
+            .enabled_features(&enabledFeatures)
+            .queue_create_infos(&queueCreateInfos)
+            .build();
+
+        if physicalDevice.supportsDynamicRendering() {
+            deviceCreateInfo.p_next = &mut dynamicRenderingFeatures as *mut _ as *const c_void;
+        }
 
         let inner =
             unsafe { instance.create_device(physicalDevice.rawDevice(), &deviceCreateInfo, None)? };
 
+        let dynamicRendering = if physicalDevice.supportsDynamicRendering() {
+            DynamicRendering::new(instance, &inner)
+        } else {
+            None
+        };
+
         let graphicsQueue =
             unsafe { inner.get_device_queue(physicalDevice.graphicsQueueIndex() as u32, 0) };
         let presentQueue =
@@ -278,30 +355,52 @@ impl Device {
 
         use gpu_allocator::*;
 
-        let allocator = Mutex::new(VulkanAllocator::new(&VulkanAllocatorCreateDesc{
+        let allocator = Mutex::new(SendAllocator(VulkanAllocator::new(&VulkanAllocatorCreateDesc{
             instance: instance.clone(),
             device: inner.clone(),
             physical_device: physicalDevice.rawDevice(),
             debug_settings: Default::default(), 
-        }));
+        })));
 
-        Ok(Arc::new(Self { inner, allocator }))
+        Ok(Arc::new(Self {
+            inner,
+            allocator,
+            enabledFeatures,
+            limits: *physicalDevice.limits(),
+            dynamicRendering,
+            shaderRegistry: ShaderRegistry::new(),
+        }))
     }
     pub (crate) fn allocateDeviceMemory(&self, desc: gpu_allocator::AllocationCreateDesc) -> gpu_allocator::Result<gpu_allocator::SubAllocation> {
-        self.allocator.lock().unwrap()
+        self.allocator.lock().unwrap().0
         .allocate(&desc)
     }
     pub (crate) fn freeDeviceMemory(&self, alloc: gpu_allocator::SubAllocation) -> gpu_allocator::Result<()> {
-        self.allocator.lock().unwrap()
+        self.allocator.lock().unwrap().0
         .free(alloc)
     }
     pub fn raw(&self) -> &ash::Device {
         &self.inner
     }
+    pub fn enabledFeatures(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.enabledFeatures
+    }
+    pub fn limits(&self) -> &vk::PhysicalDeviceLimits {
+        &self.limits
+    }
+    /// Present when VK_KHR_dynamic_rendering is enabled, in which case passes can skip VkRenderPass/VkFramebuffer objects.
+    pub fn dynamicRendering(&self) -> Option<&DynamicRendering> {
+        self.dynamicRendering.as_ref()
+    }
+    /// Graphics and compute shaders loaded on this device, see `ShaderRegistry`.
+    pub fn shaderRegistry(&self) -> &ShaderRegistry {
+        &self.shaderRegistry
+    }
     pub(super) fn getDeviceQueue(&self, index: usize) -> vk::Queue {
         unsafe { self.inner.get_device_queue(index as u32, 0) }
     }
 }
+
 impl Drop for Device {
     fn drop(&mut self) {
         unsafe { self.inner.destroy_device(None) }
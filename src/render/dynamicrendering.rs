@@ -0,0 +1,153 @@
+//! Bindings for VK_KHR_dynamic_rendering, which is newer than the Vulkan headers ash was generated from.
+
+use std::{
+    ffi::{c_void, CStr},
+    mem,
+};
+
+use ash::{version::InstanceV1_0, vk};
+use c_str_macro::c_str;
+
+pub const STRUCTURE_TYPE_RENDERING_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000044000);
+pub const STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1000044001);
+pub const STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1000044002);
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1000044003);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RenderingAttachmentInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub image_view: vk::ImageView,
+    pub image_layout: vk::ImageLayout,
+    pub resolve_mode: vk::ResolveModeFlags,
+    pub resolve_image_view: vk::ImageView,
+    pub resolve_image_layout: vk::ImageLayout,
+    pub load_op: vk::AttachmentLoadOp,
+    pub store_op: vk::AttachmentStoreOp,
+    pub clear_value: vk::ClearValue,
+}
+impl RenderingAttachmentInfoKHR {
+    pub fn new(
+        imageView: vk::ImageView,
+        imageLayout: vk::ImageLayout,
+        clearValue: vk::ClearValue,
+    ) -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR,
+            p_next: std::ptr::null(),
+            image_view: imageView,
+            image_layout: imageLayout,
+            resolve_mode: vk::ResolveModeFlags::NONE,
+            resolve_image_view: vk::ImageView::null(),
+            resolve_image_layout: vk::ImageLayout::UNDEFINED,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            clear_value: clearValue,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RenderingInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub flags: vk::Flags,
+    pub render_area: vk::Rect2D,
+    pub layer_count: u32,
+    pub view_mask: u32,
+    pub color_attachment_count: u32,
+    pub p_color_attachments: *const RenderingAttachmentInfoKHR,
+    pub p_depth_attachment: *const RenderingAttachmentInfoKHR,
+    pub p_stencil_attachment: *const RenderingAttachmentInfoKHR,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PipelineRenderingCreateInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub view_mask: u32,
+    pub color_attachment_count: u32,
+    pub p_color_attachment_formats: *const vk::Format,
+    pub depth_attachment_format: vk::Format,
+    pub stencil_attachment_format: vk::Format,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PhysicalDeviceDynamicRenderingFeaturesKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub dynamic_rendering: vk::Bool32,
+}
+impl Default for PhysicalDeviceDynamicRenderingFeaturesKHR {
+    fn default() -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR,
+            p_next: std::ptr::null_mut(),
+            dynamic_rendering: vk::TRUE,
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+type PFN_vkCmdBeginRenderingKHR =
+    extern "system" fn(commandBuffer: vk::CommandBuffer, renderingInfo: *const RenderingInfoKHR);
+#[allow(non_camel_case_types)]
+type PFN_vkCmdEndRenderingKHR = extern "system" fn(commandBuffer: vk::CommandBuffer);
+
+#[derive(Clone)]
+pub struct DynamicRendering {
+    cmdBeginRenderingKHR: PFN_vkCmdBeginRenderingKHR,
+    cmdEndRenderingKHR: PFN_vkCmdEndRenderingKHR,
+}
+impl DynamicRendering {
+    pub fn name() -> &'static CStr {
+        c_str!("VK_KHR_dynamic_rendering")
+    }
+    /// The extension and the device extensions it depends on, for a Vulkan 1.0 instance. The instance itself
+    /// needs VK_KHR_get_physical_device_properties2 enabled.
+    pub fn requiredExtensions() -> [&'static CStr; 5] {
+        [
+            Self::name(),
+            vk::KhrDepthStencilResolveFn::name(),
+            vk::KhrCreateRenderpass2Fn::name(),
+            vk::KhrMultiviewFn::name(),
+            vk::KhrMaintenance2Fn::name(),
+        ]
+    }
+    pub fn new(instance: &ash::Instance, device: &ash::Device) -> Option<Self> {
+        let load = |name: &CStr| unsafe { instance.get_device_proc_addr(device.handle(), name.as_ptr()) };
+
+        let begin = load(c_str!("vkCmdBeginRenderingKHR"))?;
+        let end = load(c_str!("vkCmdEndRenderingKHR"))?;
+
+        Some(unsafe {
+            Self {
+                cmdBeginRenderingKHR: mem::transmute::<unsafe extern "system" fn(), PFN_vkCmdBeginRenderingKHR>(
+                    begin,
+                ),
+                cmdEndRenderingKHR: mem::transmute::<unsafe extern "system" fn(), PFN_vkCmdEndRenderingKHR>(end),
+            }
+        })
+    }
+    /// # Safety
+    ///
+    /// Same as `vkCmdBeginRenderingKHR`: `commandBuffer` must be recording outside of a render pass and
+    /// `renderingInfo`, along with everything it points to, must be valid.
+    pub unsafe fn cmdBeginRendering(&self, commandBuffer: vk::CommandBuffer, renderingInfo: &RenderingInfoKHR) {
+        (self.cmdBeginRenderingKHR)(commandBuffer, renderingInfo);
+    }
+    /// # Safety
+    ///
+    /// Same as `vkCmdEndRenderingKHR`: `commandBuffer` must be inside a render pass instance begun with
+    /// `cmdBeginRendering`.
+    pub unsafe fn cmdEndRendering(&self, commandBuffer: vk::CommandBuffer) {
+        (self.cmdEndRenderingKHR)(commandBuffer);
+    }
+}
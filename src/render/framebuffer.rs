@@ -3,13 +3,36 @@ use std::sync::Arc;
 use ash::{version::DeviceV1_0, vk};
 use gpu_allocator::SubAllocation;
 
-use super::{ColorFormat, DepthStencilFormat};
+use super::{
+    dynamicrendering::{RenderingAttachmentInfoKHR, RenderingInfoKHR, STRUCTURE_TYPE_RENDERING_INFO_KHR},
+    graph::PipelineTarget,
+    texture::hasStencil,
+    CommandBuffer,
+};
 
 pub trait RenderTarget {}
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum FramebufferError {
+    #[error("A framebuffer can have at most one depth attachment, but {0} were given!")]
+    MultipleDepthAttachments(usize),
+}
+
+enum Backend {
+    Renderpass {
+        renderpass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        ownsRenderpass: bool,
+    },
+    /// VK_KHR_dynamic_rendering: the attachments are bound directly when rendering begins.
+    Dynamic,
+}
+
 pub struct Framebuffer{
     device: Arc<crate::Device>,
 
-    inner: vk::Framebuffer,
+    backend: Backend,
     images: Vec<vk::Image>,
     imageViews: Vec<vk::ImageView>,
     samplers: Vec<vk::Sampler>,
@@ -17,158 +40,208 @@ pub struct Framebuffer{
 
     colorAttachments: Vec<vk::Format>,
     depthAttachments: Vec<vk::Format>,
-    renderpass: vk::RenderPass,
+    width: u32,
+    height: u32,
 }
 impl Framebuffer {
-    pub fn create(device: &Arc<crate::Device>, renderpass: vk::RenderPass, colorAttachments: &[vk::Format], depthAttachments: &[vk::Format], width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut allocations = Vec::new();
-        let mut images = Vec::new();
-        let mut imageViews= Vec::new();
-        let mut samplers= Vec::new();
+    fn createAttachment(
+        device: &Arc<crate::Device>,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspectMask: vk::ImageAspectFlags,
+        name: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<(vk::Image, SubAllocation, vk::ImageView, vk::Sampler), Box<dyn std::error::Error>> {
+        let imageCreateInfo =
+        vk::ImageCreateInfo::builder()
+        .format(format)
+        .image_type(vk::ImageType::TYPE_2D)
+        .extent(vk::Extent3D::builder().width(width).height(height).depth(1).build())
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(usage | vk::ImageUsageFlags::SAMPLED)
+        .build();
 
-        for attachment in colorAttachments {
-            let imageCreateInfo = 
-            vk::ImageCreateInfo::builder()
-            .format(*attachment)
-            .image_type(vk::ImageType::TYPE_2D)
-            .extent(vk::Extent3D::builder().width(width).height(height).depth(1).build())
-            .mip_levels(1)
-            .array_layers(1)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
-            .build();
+        let image = unsafe {device.raw().create_image(&imageCreateInfo, None)?};
+
+        let requirements = unsafe { device.raw().get_image_memory_requirements(image) };
+
+        use gpu_allocator::*;
+        let alloc = device.allocateDeviceMemory(AllocationCreateDesc{
+            name,
+            requirements,
+            location: MemoryLocation::GpuOnly,
+            linear: false,
+        })?;
+
+        unsafe {device.raw().bind_image_memory(image, alloc.memory(), alloc.offset())?};
+
+
+        let imageViewCreateInfo =
+        vk::ImageViewCreateInfo::builder()
+        .format(format)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .subresource_range(vk::ImageSubresourceRange::builder()
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .aspect_mask(aspectMask)
+        .build())
+        .image(image)
+        ;
 
-            let image = unsafe {device.raw().create_image(&imageCreateInfo, None)?};
-
-            let requirements = unsafe { device.raw().get_image_memory_requirements(image) };
-            
-            use gpu_allocator::*;
-            let alloc = device.allocateDeviceMemory(AllocationCreateDesc{
-                name: "ColorAttachment",
-                requirements,
-                location: MemoryLocation::GpuOnly,
-                linear: false,
-            })?;
-
-            unsafe {device.raw().bind_image_memory(image, alloc.memory(), alloc.offset())?};
-            
-
-            let imageViewCreateInfo = 
-            vk::ImageViewCreateInfo::builder()
-            .format(*attachment)
-            .subresource_range(vk::ImageSubresourceRange::builder().base_mip_level(0).level_count(1).base_array_layer(1).layer_count(1).build())
-            .image(image)
-            ;
-
-            let imageView = 
-            unsafe {device.raw().create_image_view(&imageViewCreateInfo, None)}?;
-            
-            let samplerCreateInfo = 
-            vk::SamplerCreateInfo::builder()
-            .mag_filter(vk::Filter::LINEAR)
-            .min_filter(vk::Filter::LINEAR)
-            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .mip_lod_bias(0.0)
-            .max_anisotropy(1.0)
-            .min_lod(0.0)
-            .max_lod(1.0)
+        let imageView =
+        unsafe {device.raw().create_image_view(&imageViewCreateInfo, None)}?;
+
+        let samplerCreateInfo =
+        vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .mip_lod_bias(0.0)
+        .max_anisotropy(1.0)
+        .min_lod(0.0)
+        .max_lod(1.0)
+        .build();
+
+        let sampler = unsafe {device.raw().create_sampler(&samplerCreateInfo, None)}?;
+
+        Ok((image, alloc, imageView, sampler))
+    }
+    fn depthAspect(format: vk::Format) -> vk::ImageAspectFlags {
+        if hasStencil(format) {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        } else {
+            vk::ImageAspectFlags::DEPTH
+        }
+    }
+    /// Creates a render pass with a single subpass writing to every attachment, leaving them ready to be sampled.
+    fn createCompatibleRenderpass(
+        device: &Arc<crate::Device>,
+        colorAttachments: &[vk::Format],
+        depthAttachments: &[vk::Format],
+    ) -> Result<vk::RenderPass, Box<dyn std::error::Error>> {
+        let attachments: Vec<_> = colorAttachments
+            .iter()
+            .chain(depthAttachments.iter())
+            .map(|&format| {
+                vk::AttachmentDescription::builder()
+                    .format(format)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
+                    .stencil_store_op(vk::AttachmentStoreOp::STORE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .build()
+            })
+            .collect();
+
+        let colorReferences: Vec<_> = (0..colorAttachments.len())
+            .map(|i| {
+                vk::AttachmentReference::builder()
+                    .attachment(i as u32)
+                    .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .build()
+            })
+            .collect();
+
+        let depthReference = vk::AttachmentReference::builder()
+            .attachment(colorAttachments.len() as u32)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
             .build();
 
-            let sampler = unsafe {device.raw().create_sampler(&samplerCreateInfo, None)}?;
+        let mut subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&colorReferences);
+
+        if !depthAttachments.is_empty() {
+            subpass = subpass.depth_stencil_attachment(&depthReference);
+        }
 
+        let subpasses = [subpass.build()];
+
+        let renderpassCreateInfo = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&subpasses);
+
+        Ok(unsafe { device.raw().create_render_pass(&renderpassCreateInfo, None)? })
+    }
+    fn build(device: &Arc<crate::Device>, renderpass: Option<(vk::RenderPass, bool)>, colorAttachments: &[vk::Format], depthAttachments: &[vk::Format], width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut allocations = Vec::new();
+        let mut images = Vec::new();
+        let mut imageViews= Vec::new();
+        let mut samplers= Vec::new();
+
+        for &attachment in colorAttachments {
+            let (image, alloc, imageView, sampler) = Self::createAttachment(
+                device,
+                attachment,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                vk::ImageAspectFlags::COLOR,
+                "ColorAttachment",
+                width,
+                height,
+            )?;
 
             allocations.push(alloc);
             images.push(image);
             imageViews.push(imageView);
             samplers.push(sampler);
-        } 
-
-        for attachment in depthAttachments {
-            let imageCreateInfo = 
-            vk::ImageCreateInfo::builder()
-            .format(*attachment)
-            .image_type(vk::ImageType::TYPE_2D)
-            .extent(vk::Extent3D::builder().width(width).height(height).depth(1).build())
-            .mip_levels(1)
-            .array_layers(1)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
-            .build();
-
-            let image = unsafe {device.raw().create_image(&imageCreateInfo, None)?};
-
-            let requirements = unsafe { device.raw().get_image_memory_requirements(image) };
-            
-            use gpu_allocator::*;
-            let alloc = device.allocateDeviceMemory(AllocationCreateDesc{
-                name: "DepthAttachment",
-                requirements,
-                location: MemoryLocation::GpuOnly,
-                linear: false,
-            })?;
-
-            unsafe {device.raw().bind_image_memory(image, alloc.memory(), alloc.offset())?};
-            
-
-            let imageViewCreateInfo = 
-            vk::ImageViewCreateInfo::builder()
-            .format(*attachment)
-            .subresource_range(vk::ImageSubresourceRange::builder()
-            .base_mip_level(0)
-            .level_count(1)
-            .base_array_layer(1)
-            .layer_count(1)
-            .aspect_mask(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL)
-            .build())
-            .image(image)
-            ;
-
-            let imageView = 
-            unsafe {device.raw().create_image_view(&imageViewCreateInfo, None)}?;
-            
-            let samplerCreateInfo = 
-            vk::SamplerCreateInfo::builder()
-            .mag_filter(vk::Filter::LINEAR)
-            .min_filter(vk::Filter::LINEAR)
-            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
-            .mip_lod_bias(0.0)
-            .max_anisotropy(1.0)
-            .min_lod(0.0)
-            .max_lod(1.0)
-            .build();
-
-            let sampler = unsafe {device.raw().create_sampler(&samplerCreateInfo, None)}?;
+        }
 
+        for &attachment in depthAttachments {
+            let (image, alloc, imageView, sampler) = Self::createAttachment(
+                device,
+                attachment,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                Self::depthAspect(attachment),
+                "DepthAttachment",
+                width,
+                height,
+            )?;
 
             allocations.push(alloc);
             images.push(image);
             imageViews.push(imageView);
             samplers.push(sampler);
-        }  
-
-        let framebufferCreateInfo = 
-        vk::FramebufferCreateInfo::builder()
-        .attachments(&imageViews)
-        .width(width)
-        .height(height)
-        .layers(1)
-        .build();
+        }
 
-        let inner = unsafe {device.raw().create_framebuffer(&framebufferCreateInfo, None)}?;
+        let backend = match renderpass {
+            Some((renderpass, ownsRenderpass)) => {
+                let framebufferCreateInfo =
+                vk::FramebufferCreateInfo::builder()
+                .render_pass(renderpass)
+                .attachments(&imageViews)
+                .width(width)
+                .height(height)
+                .layers(1)
+                .build();
+
+                let framebuffer = unsafe {device.raw().create_framebuffer(&framebufferCreateInfo, None)}?;
+
+                Backend::Renderpass {
+                    renderpass,
+                    framebuffer,
+                    ownsRenderpass,
+                }
+            }
+            None => Backend::Dynamic,
+        };
 
         let colorAttachments = colorAttachments.to_vec();
         let depthAttachments = depthAttachments.to_vec();
         Ok(
         Self {
-            inner, 
+            backend,
             images,
             imageViews,
             allocations,
@@ -176,21 +249,254 @@ impl Framebuffer {
             samplers,
             colorAttachments,
             depthAttachments,
-            renderpass
+            width,
+            height,
         }
     )
     }
+    pub fn create(device: &Arc<crate::Device>, renderpass: vk::RenderPass, colorAttachments: &[vk::Format], depthAttachments: &[vk::Format], width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::build(device, Some((renderpass, false)), colorAttachments, depthAttachments, width, height)
+    }
+    /// Creates a framebuffer that doesn't need a render pass from the caller.
+    ///
+    /// Uses dynamic rendering when the device supports it, otherwise falls back to a render pass and
+    /// framebuffer object owned by this framebuffer. Either way, pipelines should be created for `target()`.
+    pub fn createDynamic(device: &Arc<crate::Device>, colorAttachments: &[vk::Format], depthAttachments: &[vk::Format], width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        // A subpass and a dynamic rendering pass both take a single depth attachment.
+        if depthAttachments.len() > 1 {
+            return Err(Box::new(FramebufferError::MultipleDepthAttachments(depthAttachments.len())));
+        }
+
+        if device.dynamicRendering().is_some() {
+            return Self::build(device, None, colorAttachments, depthAttachments, width, height);
+        }
+
+        let renderpass = Self::createCompatibleRenderpass(device, colorAttachments, depthAttachments)?;
+
+        Self::build(device, Some((renderpass, true)), colorAttachments, depthAttachments, width, height).map_err(|err| {
+            unsafe { device.raw().destroy_render_pass(renderpass, None) };
+            err
+        })
+    }
+
+    pub fn recreate(mut self, width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>>{
+        let renderpass = match &self.backend {
+            Backend::Renderpass { renderpass, ownsRenderpass, .. } => Some((*renderpass, *ownsRenderpass)),
+            Backend::Dynamic => None,
+        };
 
-    pub fn recreate(self, width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>>{
-        Self::create(&self.device, self.renderpass, &self.colorAttachments, &self.depthAttachments, width, height)
+        let recreated = Self::build(&self.device, renderpass, &self.colorAttachments, &self.depthAttachments, width, height)?;
+
+        // The render pass has moved over to the new framebuffer; until now, the old one still owned it.
+        if let Backend::Renderpass { ownsRenderpass, .. } = &mut self.backend {
+            *ownsRenderpass = false;
+        }
+
+        Ok(recreated)
+    }
+    /// What pipelines drawing into this framebuffer have to be created for.
+    pub fn target(&self) -> PipelineTarget {
+        match &self.backend {
+            Backend::Renderpass { renderpass, .. } => {
+                PipelineTarget::Renderpass(*renderpass, self.colorAttachments.len() as u32)
+            }
+            Backend::Dynamic => PipelineTarget::Dynamic {
+                colorFormats: self.colorAttachments.clone(),
+                depthFormat: self.depthAttachments.first().copied(),
+            },
+        }
+    }
+    fn renderArea(&self) -> vk::Rect2D {
+        vk::Rect2D::builder()
+            .extent(vk::Extent2D::builder().width(self.width).height(self.height).build())
+            .build()
+    }
+    fn transitionAttachments(&self, commandBuffer: &CommandBuffer, toAttachment: bool) {
+        let colorCount = self.colorAttachments.len();
+
+        let barriers: Vec<_> = self
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, &image)| {
+                let (aspectMask, attachmentLayout, attachmentAccess) = if i < colorCount {
+                    (
+                        vk::ImageAspectFlags::COLOR,
+                        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                        vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    )
+                } else {
+                    (
+                        Self::depthAspect(self.depthAttachments[i - colorCount]),
+                        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                    )
+                };
+
+                let (oldLayout, newLayout, srcAccess, dstAccess) = if toAttachment {
+                    (
+                        vk::ImageLayout::UNDEFINED,
+                        attachmentLayout,
+                        vk::AccessFlags::empty(),
+                        attachmentAccess,
+                    )
+                } else {
+                    (
+                        attachmentLayout,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        attachmentAccess,
+                        vk::AccessFlags::SHADER_READ,
+                    )
+                };
+
+                vk::ImageMemoryBarrier::builder()
+                    .image(image)
+                    .old_layout(oldLayout)
+                    .new_layout(newLayout)
+                    .src_access_mask(srcAccess)
+                    .dst_access_mask(dstAccess)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(aspectMask)
+                            .level_count(1)
+                            .layer_count(1)
+                            .build(),
+                    )
+                    .build()
+            })
+            .collect();
+
+        let attachmentStages = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+            | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+
+        let (srcStage, dstStage) = if toAttachment {
+            (vk::PipelineStageFlags::TOP_OF_PIPE, attachmentStages)
+        } else {
+            (attachmentStages, vk::PipelineStageFlags::FRAGMENT_SHADER)
+        };
+
+        unsafe {
+            self.device.raw().cmd_pipeline_barrier(
+                commandBuffer.raw(),
+                srcStage,
+                dstStage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+    }
+    /// Starts rendering into the attachments, clearing them with `clearValues` (color attachments first, then depth).
+    pub fn begin(&self, commandBuffer: &CommandBuffer, clearValues: &[vk::ClearValue]) {
+        match &self.backend {
+            Backend::Renderpass { renderpass, framebuffer, .. } => {
+                let beginInfo = vk::RenderPassBeginInfo::builder()
+                    .render_pass(*renderpass)
+                    .framebuffer(*framebuffer)
+                    .render_area(self.renderArea())
+                    .clear_values(clearValues);
+
+                unsafe {
+                    self.device.raw().cmd_begin_render_pass(
+                        commandBuffer.raw(),
+                        &beginInfo,
+                        vk::SubpassContents::INLINE,
+                    );
+                }
+            }
+            Backend::Dynamic => {
+                self.transitionAttachments(commandBuffer, true);
+
+                let clearValue = |i: usize| clearValues.get(i).copied().unwrap_or_default();
+
+                let colorCount = self.colorAttachments.len();
+
+                let colorAttachments: Vec<_> = self.imageViews[..colorCount]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &view)| {
+                        RenderingAttachmentInfoKHR::new(
+                            view,
+                            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                            clearValue(i),
+                        )
+                    })
+                    .collect();
+
+                let depthAttachment = self.imageViews.get(colorCount).map(|&view| {
+                    RenderingAttachmentInfoKHR::new(
+                        view,
+                        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                        clearValue(colorCount),
+                    )
+                });
+
+                let stencilAttachment = match (&depthAttachment, self.depthAttachments.first()) {
+                    (Some(depthAttachment), Some(&format)) if hasStencil(format) => {
+                        depthAttachment as *const _
+                    }
+                    _ => std::ptr::null(),
+                };
+
+                let renderingInfo = RenderingInfoKHR {
+                    s_type: STRUCTURE_TYPE_RENDERING_INFO_KHR,
+                    p_next: std::ptr::null(),
+                    flags: 0,
+                    render_area: self.renderArea(),
+                    layer_count: 1,
+                    view_mask: 0,
+                    color_attachment_count: colorAttachments.len() as u32,
+                    p_color_attachments: colorAttachments.as_ptr(),
+                    p_depth_attachment: depthAttachment
+                        .as_ref()
+                        .map_or(std::ptr::null(), |attachment| attachment as *const _),
+                    p_stencil_attachment: stencilAttachment,
+                };
+
+                unsafe {
+                    self.device
+                        .dynamicRendering()
+                        .unwrap()
+                        .cmdBeginRendering(commandBuffer.raw(), &renderingInfo);
+                }
+            }
+        }
+    }
+    /// Finishes rendering; afterwards the attachments can be sampled.
+    pub fn end(&self, commandBuffer: &CommandBuffer) {
+        match &self.backend {
+            Backend::Renderpass { .. } => unsafe {
+                self.device.raw().cmd_end_render_pass(commandBuffer.raw());
+            },
+            Backend::Dynamic => {
+                unsafe {
+                    self.device
+                        .dynamicRendering()
+                        .unwrap()
+                        .cmdEndRendering(commandBuffer.raw());
+                }
+
+                self.transitionAttachments(commandBuffer, false);
+            }
+        }
     }
 }
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
         unsafe {
-        self.device.raw().destroy_framebuffer(self.inner, None);
-        
+        if let Backend::Renderpass { renderpass, framebuffer, ownsRenderpass } = self.backend {
+            self.device.raw().destroy_framebuffer(framebuffer, None);
+
+            if ownsRenderpass {
+                self.device.raw().destroy_render_pass(renderpass, None);
+            }
+        }
+
         for &sampler in &self.samplers {
             self.device.raw().destroy_sampler(sampler, None);
         }
@@ -205,4 +511,4 @@ impl Drop for Framebuffer {
         }
     }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::Shader;
+
+use super::pipeline::{Pipeline, PipelineCreateInfo, PipelineDesciptor, PipelineKey, PipelineTarget};
+
+/// Creates each distinct pipeline once and hands out shared references afterwards.
+///
+/// Descriptors that differ only in their specialization values produce separate pipelines.
+/// The cache can be shared between threads; pipelines are created outside of the lock.
+pub struct PipelineCache {
+    device: Arc<crate::Device>,
+    pipelines: Mutex<HashMap<PipelineKey, Arc<Pipeline>>>,
+}
+impl PipelineCache {
+    pub fn new(device: &Arc<crate::Device>) -> Self {
+        Self {
+            device: device.clone(),
+            pipelines: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Returns the pipeline if it has already been created.
+    pub fn cached(&self, descriptor: &PipelineDesciptor, target: &PipelineTarget) -> Option<Arc<Pipeline>> {
+        self.pipelines
+            .lock()
+            .unwrap()
+            .get(&descriptor.key(target))
+            .cloned()
+    }
+    pub fn get(
+        &self,
+        descriptor: &PipelineDesciptor,
+        target: &PipelineTarget,
+    ) -> Result<Arc<Pipeline>, Box<dyn std::error::Error>> {
+        if let Some(pipeline) = self.cached(descriptor, target) {
+            if !pipeline.isOutdated() {
+                return Ok(pipeline);
+            }
+
+            return Ok(self.replace(descriptor.key(target), pipeline));
+        }
+
+        let pipeline = Arc::new(PipelineCreateInfo::new(descriptor.clone()).create(&self.device, target)?);
+
+        // Another thread may have created the same pipeline in the meantime, keep whichever got in first.
+        Ok(self
+            .pipelines
+            .lock()
+            .unwrap()
+            .entry(descriptor.key(target))
+            .or_insert(pipeline)
+            .clone())
+    }
+    /// Rebuilds every cached pipeline created from `shader`, after it has been reloaded.
+    ///
+    /// Returns how many pipelines were rebuilt. Pipelines that fail to build are kept as they are.
+    pub fn rebuild(&self, shader: &Arc<Shader>) -> usize {
+        let outdated: Vec<_> = self
+            .pipelines
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, pipeline)| Arc::ptr_eq(pipeline.shader(), shader) && pipeline.isOutdated())
+            .map(|(key, pipeline)| (key.clone(), pipeline.clone()))
+            .collect();
+
+        let count = outdated.len();
+
+        for (key, pipeline) in outdated {
+            self.replace(key, pipeline);
+        }
+
+        count
+    }
+    /// Swaps an outdated pipeline for a rebuilt one, keeping the old one if that fails.
+    fn replace(&self, key: PipelineKey, pipeline: Arc<Pipeline>) -> Arc<Pipeline> {
+        match pipeline.rebuild() {
+            Ok(rebuilt) => {
+                let rebuilt = Arc::new(rebuilt);
+
+                self.pipelines.lock().unwrap().insert(key, rebuilt.clone());
+
+                rebuilt
+            }
+            Err(err) => {
+                log::error!(
+                    "Failed to rebuild pipeline for shader {:?}, keeping the old one: {}",
+                    pipeline.shader().name(),
+                    err
+                );
+
+                pipeline
+            }
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.pipelines.lock().unwrap().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.lock().unwrap().is_empty()
+    }
+    pub fn clear(&self) {
+        self.pipelines.lock().unwrap().clear();
+    }
+}
@@ -1,11 +1,22 @@
 //pub mod renderpass;
+mod cache;
 mod node;
 mod pipeline;
+mod pipelinecompiler;
+mod pushconstants;
+mod specialization;
 mod renderpass;
 mod compiler;
 
 use ash::vk;
-pub use pipeline::PipelineCreateInfo;
+pub use cache::PipelineCache;
+pub use pipeline::ComputePipeline;
+pub use pipeline::{PipelineCreateInfo, PipelineTarget};
+pub use pipelinecompiler::{PipelineCompiler, PipelineFallback, PipelineHandle};
+pub use pushconstants::{
+    PushConstantError, PushConstantField, PushConstantValue, PushConstants, PushConstantsInfo,
+};
+pub use specialization::{Specialization, SpecializationError, SpecializationValue};
 
 use self::renderpass::Renderpass;
 //pub use renderpass::RenderpassBuilder;
@@ -3,9 +3,32 @@ use ash::{
     vk::{self, ColorComponentFlags, PipelineInputAssemblyStateCreateInfo, SampleCountFlags},
 };
 
-use std::{cmp::max, error::Error, ffi::CString, sync::Arc};
+use std::{
+    any::TypeId,
+    cmp::max,
+    error::Error,
+    ffi::{c_void, CStr, CString},
+    sync::Arc,
+};
+
+use crate::{
+    render::{
+        dynamicrendering::{PipelineRenderingCreateInfoKHR, STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR},
+        shader::{
+            reflection::{DescriptorBinding, ReflectionData},
+            ShaderInfo,
+        },
+        texture::hasStencil,
+    },
+    CommandBuffer, ComputeShader, Shader,
+};
 
-use crate::Shader;
+use super::{
+    pushconstants::{
+        PushConstantError, PushConstantLayout, PushConstantValue, PushConstants, PushConstantsInfo,
+    },
+    specialization::{Specialization, SpecializationValue, StageSpecialization},
+};
 
 pub enum BlendMode {
     None,
@@ -14,10 +37,13 @@ pub enum BlendMode {
 //     scissor: (f32, f32, f32, f32),
 //     msaa: bool,
 // }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PrimitiveTopology {
     Point,
     Triangle,
     Line,
+    /// Patches for tessellation, see `PipelineDesciptor::patchControlPoints`.
+    Patch,
 }
 impl Default for PrimitiveTopology {
     fn default() -> Self {
@@ -25,16 +51,159 @@ impl Default for PrimitiveTopology {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+impl Into<vk::CullModeFlags> for CullMode {
+    fn into(self) -> vk::CullModeFlags {
+        match self {
+            CullMode::None => vk::CullModeFlags::NONE,
+            CullMode::Front => vk::CullModeFlags::FRONT,
+            CullMode::Back => vk::CullModeFlags::BACK,
+            CullMode::FrontAndBack => vk::CullModeFlags::FRONT_AND_BACK,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrontFace {
+    Clockwise,
+    CounterClockwise,
+}
+impl Into<vk::FrontFace> for FrontFace {
+    fn into(self) -> vk::FrontFace {
+        match self {
+            FrontFace::Clockwise => vk::FrontFace::CLOCKWISE,
+            FrontFace::CounterClockwise => vk::FrontFace::COUNTER_CLOCKWISE,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+impl Into<vk::PolygonMode> for PolygonMode {
+    fn into(self) -> vk::PolygonMode {
+        match self {
+            PolygonMode::Fill => vk::PolygonMode::FILL,
+            PolygonMode::Line => vk::PolygonMode::LINE,
+            PolygonMode::Point => vk::PolygonMode::POINT,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DepthBias {
+    pub constantFactor: f32,
+    pub slopeFactor: f32,
+    pub clamp: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RasterizationState {
+    pub cullMode: CullMode,
+    pub frontFace: FrontFace,
+    pub polygonMode: PolygonMode,
+    pub depthBias: Option<DepthBias>,
+    pub depthClamp: bool,
+    pub lineWidth: f32,
+}
+impl Default for RasterizationState {
+    fn default() -> Self {
+        Self {
+            cullMode: CullMode::Back,
+            frontFace: FrontFace::Clockwise,
+            polygonMode: PolygonMode::Fill,
+            depthBias: None,
+            depthClamp: false,
+            lineWidth: 1.0,
+        }
+    }
+}
+impl RasterizationState {
+    fn floatBits(&self) -> [u32; 4] {
+        let bias = self.depthBias.unwrap_or_default();
+
+        [
+            bias.constantFactor.to_bits(),
+            bias.slopeFactor.to_bits(),
+            bias.clamp.to_bits(),
+            self.lineWidth.to_bits(),
+        ]
+    }
+    fn checkFeatures(&self, features: &vk::PhysicalDeviceFeatures) -> Result<(), PipelineCreateError> {
+        if self.polygonMode != PolygonMode::Fill && features.fill_mode_non_solid != vk::TRUE {
+            return Err(PipelineCreateError::FeatureNotEnabled("fillModeNonSolid"));
+        }
+        if self.depthClamp && features.depth_clamp != vk::TRUE {
+            return Err(PipelineCreateError::FeatureNotEnabled("depthClamp"));
+        }
+        if self.lineWidth != 1.0 && features.wide_lines != vk::TRUE {
+            return Err(PipelineCreateError::FeatureNotEnabled("wideLines"));
+        }
+        if let Some(bias) = self.depthBias {
+            if bias.clamp != 0.0 && features.depth_bias_clamp != vk::TRUE {
+                return Err(PipelineCreateError::FeatureNotEnabled("depthBiasClamp"));
+            }
+        }
+
+        Ok(())
+    }
+}
+// Compare float bit patterns so that the state can key the pipeline cache.
+impl PartialEq for RasterizationState {
+    fn eq(&self, other: &Self) -> bool {
+        self.cullMode == other.cullMode
+            && self.frontFace == other.frontFace
+            && self.polygonMode == other.polygonMode
+            && self.depthBias.is_some() == other.depthBias.is_some()
+            && self.depthClamp == other.depthClamp
+            && self.floatBits() == other.floatBits()
+    }
+}
+impl Eq for RasterizationState {}
+impl std::hash::Hash for RasterizationState {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.cullMode.hash(state);
+        self.frontFace.hash(state);
+        self.polygonMode.hash(state);
+        self.depthBias.is_some().hash(state);
+        self.depthClamp.hash(state);
+        self.floatBits().hash(state);
+    }
+}
+
+/// What a graphics pipeline renders into.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PipelineTarget {
+    /// Subpass 0 of a render pass (or a compatible one), and the number of color attachments it writes.
+    Renderpass(vk::RenderPass, u32),
+    /// Attachment formats for VK_KHR_dynamic_rendering, without any render pass object.
+    Dynamic {
+        colorFormats: Vec<vk::Format>,
+        depthFormat: Option<vk::Format>,
+    },
+}
+/// A render pass with a single color attachment, like the one drawing to the swapchain.
+impl From<vk::RenderPass> for PipelineTarget {
+    fn from(renderpass: vk::RenderPass) -> Self {
+        Self::Renderpass(renderpass, 1)
+    }
+}
+
+#[derive(Clone)]
 pub struct PipelineCreateInfo {
-    pub(crate) shaderStageCreateInfos: Vec<vk::PipelineShaderStageCreateInfo>,
-    pub(crate) inputAssemblyCreateInfo: vk::PipelineInputAssemblyStateCreateInfo,
-    pub(crate) viewportStateCreateInfo: vk::PipelineViewportStateCreateInfo,
-    pub(crate) rasterizationStateCreateInfo: vk::PipelineRasterizationStateCreateInfo,
-    pub(crate) multisampleStateCreateInfo: vk::PipelineMultisampleStateCreateInfo,
-    pub(crate) colorBlendStateCreateInfo: vk::PipelineColorBlendStateCreateInfo,
-    pub(crate) dynamicStateCreateInfo: vk::PipelineDynamicStateCreateInfo,
-    pub(crate) vertexInputStateCreateInfo: vk::PipelineVertexInputStateCreateInfo,
+    pub(crate) topology: PrimitiveTopology,
+    pub(crate) rasterization: RasterizationState,
+    pub(crate) msaa: bool,
     pub(crate) shader: Arc<crate::Shader>,
+    pub(crate) pushConstants: Option<PushConstantsInfo>,
+    pub(crate) specialization: Specialization,
+    pub(crate) patchControlPoints: Option<u32>,
 }
 
 use thiserror::Error;
@@ -42,21 +211,273 @@ use thiserror::Error;
 pub enum PipelineCreateError {
     #[error("No shader provided for pipeline creation!")]
     NoShader,
+    #[error("Pipeline requires the {0:?} device feature, which is not supported by this GPU!")]
+    FeatureNotEnabled(&'static str),
+    #[error("Pipeline targets dynamic rendering, but VK_KHR_dynamic_rendering is not enabled on this device!")]
+    DynamicRenderingUnsupported,
+    #[error("Shader {0:?} has tessellation stages, so the pipeline topology has to be PrimitiveTopology::Patch!")]
+    PatchTopologyRequired(String),
+    #[error("PrimitiveTopology::Patch requires tessellation stages, but shader {0:?} has none!")]
+    MissingTessellationStages(String),
+    #[error("Shader {0:?} has tessellation stages, so the pipeline needs patchControlPoints!")]
+    PatchControlPointsRequired(String),
+    #[error("Patches need between 1 and {1} control points on this GPU, but {0} were requested!")]
+    InvalidPatchControlPoints(u32, u32),
 }
+#[derive(Clone)]
 pub struct VertexInputLayout {}
+#[derive(Clone)]
 pub struct PipelineDesciptor {
     topology: PrimitiveTopology,
+    rasterization: RasterizationState,
     msaa: bool,
     shader: Arc<Shader>,
     vertexInputLayout: VertexInputLayout,
+    pushConstants: Option<PushConstantsInfo>,
+    specialization: Specialization,
+    patchControlPoints: Option<u32>,
     //blend
 }
+impl PipelineDesciptor {
+    pub fn new(shader: Arc<Shader>) -> Self {
+        Self {
+            topology: PrimitiveTopology::default(),
+            rasterization: RasterizationState::default(),
+            msaa: false,
+            shader,
+            vertexInputLayout: VertexInputLayout {},
+            pushConstants: None,
+            specialization: Specialization::new(),
+            patchControlPoints: None,
+        }
+    }
+    pub fn topology(&mut self, topology: PrimitiveTopology) -> &mut Self {
+        self.topology = topology;
+
+        self
+    }
+    pub fn msaa(&mut self, msaa: bool) -> &mut Self {
+        self.msaa = msaa;
+
+        self
+    }
+    pub fn rasterization(&mut self, rasterization: RasterizationState) -> &mut Self {
+        self.rasterization = rasterization;
+
+        self
+    }
+    pub fn cullMode(&mut self, cullMode: CullMode) -> &mut Self {
+        self.rasterization.cullMode = cullMode;
+
+        self
+    }
+    pub fn frontFace(&mut self, frontFace: FrontFace) -> &mut Self {
+        self.rasterization.frontFace = frontFace;
+
+        self
+    }
+    /// Anything other than `PolygonMode::Fill` requires the `fillModeNonSolid` feature.
+    pub fn polygonMode(&mut self, polygonMode: PolygonMode) -> &mut Self {
+        self.rasterization.polygonMode = polygonMode;
+
+        self
+    }
+    /// A non-zero `clamp` requires the `depthBiasClamp` feature.
+    pub fn depthBias(&mut self, constantFactor: f32, slopeFactor: f32, clamp: f32) -> &mut Self {
+        self.rasterization.depthBias.replace(DepthBias {
+            constantFactor,
+            slopeFactor,
+            clamp,
+        });
+
+        self
+    }
+    /// Requires the `depthClamp` feature.
+    pub fn depthClamp(&mut self, depthClamp: bool) -> &mut Self {
+        self.rasterization.depthClamp = depthClamp;
+
+        self
+    }
+    /// Widths other than 1.0 require the `wideLines` feature.
+    pub fn lineWidth(&mut self, lineWidth: f32) -> &mut Self {
+        self.rasterization.lineWidth = lineWidth;
+
+        self
+    }
+    /// Declares the struct used to upload push constants; its layout is checked against the shader when the pipeline is created.
+    pub fn pushConstants<T: PushConstants>(&mut self) -> &mut Self {
+        self.pushConstants.replace(PushConstantsInfo::of::<T>());
+
+        self
+    }
+    pub fn specialize<T: Into<SpecializationValue>>(&mut self, name: &str, value: T) -> &mut Self {
+        self.specialization.set(name, value);
+
+        self
+    }
+    pub fn specializeId<T: Into<SpecializationValue>>(&mut self, id: u32, value: T) -> &mut Self {
+        self.specialization.setId(id, value);
+
+        self
+    }
+    /// Number of vertices per input patch, required for shaders with tessellation stages.
+    pub fn patchControlPoints(&mut self, patchControlPoints: u32) -> &mut Self {
+        self.patchControlPoints.replace(patchControlPoints);
+
+        self
+    }
+    pub(crate) fn key(&self, target: &PipelineTarget) -> PipelineKey {
+        PipelineKey {
+            shader: Arc::as_ptr(&self.shader) as usize,
+            topology: self.topology,
+            rasterization: self.rasterization,
+            msaa: self.msaa,
+            pushConstants: self.pushConstants.as_ref().map(|info| info.typeId()),
+            specialization: self.specialization.clone(),
+            patchControlPoints: self.patchControlPoints,
+            target: target.clone(),
+        }
+    }
+}
+
+/// Everything that distinguishes one cached pipeline from another.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PipelineKey {
+    shader: usize,
+    topology: PrimitiveTopology,
+    rasterization: RasterizationState,
+    msaa: bool,
+    pushConstants: Option<TypeId>,
+    specialization: Specialization,
+    patchControlPoints: Option<u32>,
+    target: PipelineTarget,
+}
+
+fn shaderStageCreateInfo(
+    stage: vk::ShaderStageFlags,
+    info: &ShaderInfo,
+    entryPoint: &CStr,
+    specializationInfo: Option<&vk::SpecializationInfo>,
+) -> vk::PipelineShaderStageCreateInfo {
+    let builder = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(stage)
+        .module(info.module)
+        .name(entryPoint);
+
+    match specializationInfo {
+        Some(specializationInfo) => builder.specialization_info(specializationInfo).build(),
+        None => builder.build(),
+    }
+}
+
+/// Checks the declared push constant struct against the shader, which must then have a push constant block.
+fn validatePushConstants(
+    shaderName: &str,
+    layout: Option<&PushConstantLayout>,
+    info: Option<&PushConstantsInfo>,
+) -> Result<(), PushConstantError> {
+    match (layout, info) {
+        (Some(layout), Some(info)) => layout.validate(info),
+        (None, Some(_)) => Err(PushConstantError::NoPushConstants(shaderName.to_owned())),
+        _ => Ok(()),
+    }
+}
+/// One descriptor set layout per set from 0 to the highest set the bindings use; unused sets get an empty layout.
+fn createSetLayouts(
+    device: &crate::Device,
+    stages: vk::ShaderStageFlags,
+    bindings: &[DescriptorBinding],
+) -> Result<Vec<vk::DescriptorSetLayout>, vk::Result> {
+    let setCount = bindings.iter().map(|binding| binding.set() + 1).max().unwrap_or(0);
+
+    let mut setLayouts = Vec::with_capacity(setCount as usize);
+
+    for set in 0..setCount {
+        let layoutBindings: Vec<_> = bindings
+            .iter()
+            .filter(|binding| binding.set() == set)
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding.binding())
+                    .descriptor_type(binding.descriptorType().into())
+                    .descriptor_count(binding.count())
+                    .stage_flags(stages)
+                    .build()
+            })
+            .collect();
+
+        let createInfo = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&layoutBindings)
+            .build();
+
+        match unsafe { device.raw().create_descriptor_set_layout(&createInfo, None) } {
+            Ok(setLayout) => setLayouts.push(setLayout),
+            Err(err) => {
+                destroySetLayouts(device, &setLayouts);
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(setLayouts)
+}
+fn destroySetLayouts(device: &crate::Device, setLayouts: &[vk::DescriptorSetLayout]) {
+    for &setLayout in setLayouts {
+        unsafe { device.raw().destroy_descriptor_set_layout(setLayout, None) };
+    }
+}
 
 pub struct Pipeline {
     device: Arc<crate::Device>,
     createInfo: PipelineCreateInfo,
+    target: PipelineTarget,
+    shaderGeneration: u64,
     pipeline: vk::Pipeline,
     layout: vk::PipelineLayout,
+    pushConstantLayout: Option<PushConstantLayout>,
+}
+impl Pipeline {
+    pub fn shader(&self) -> &Arc<Shader> {
+        &self.createInfo.shader
+    }
+    /// True once the shader has been reloaded since this pipeline was created.
+    pub fn isOutdated(&self) -> bool {
+        self.createInfo.shader.generation() != self.shaderGeneration
+    }
+    /// Creates the same pipeline again from the shader's current modules.
+    ///
+    /// `PipelineCache::rebuild` does this for cached pipelines; pipelines created outside of a cache have to be
+    /// replaced by whoever holds them.
+    pub fn rebuild(&self) -> Result<Pipeline, Box<dyn std::error::Error>> {
+        self.createInfo.clone().create(&self.device, &self.target)
+    }
+    pub fn pushConstantLayout(&self) -> Option<&PushConstantLayout> {
+        self.pushConstantLayout.as_ref()
+    }
+    fn requirePushConstantLayout(&self) -> Result<&PushConstantLayout, PushConstantError> {
+        self.pushConstantLayout
+            .as_ref()
+            .ok_or_else(|| PushConstantError::NoPushConstants(self.createInfo.shader.name.to_owned()))
+    }
+    pub fn pushConstants<T: PushConstants>(
+        &self,
+        commandBuffer: &CommandBuffer,
+        data: &T,
+    ) -> Result<(), PushConstantError> {
+        let validatedType = self.createInfo.pushConstants.as_ref().map(|info| info.typeId());
+
+        self.requirePushConstantLayout()?
+            .push(&self.device, commandBuffer, self.layout, validatedType, data)
+    }
+    pub fn setPushConstant<T: PushConstantValue>(
+        &self,
+        commandBuffer: &CommandBuffer,
+        name: &str,
+        value: &T,
+    ) -> Result<(), PushConstantError> {
+        self.requirePushConstantLayout()?
+            .pushMember(&self.device, commandBuffer, self.layout, name, value)
+    }
 }
 impl Drop for Pipeline {
     fn drop(&mut self) {
@@ -68,12 +489,131 @@ impl Drop for Pipeline {
 }
 impl PipelineCreateInfo {
     pub(crate) fn new(descriptor: PipelineDesciptor) -> Self {
-        let topology = match descriptor.topology {
+        Self {
+            topology: descriptor.topology,
+            rasterization: descriptor.rasterization,
+            msaa: descriptor.msaa,
+            shader: descriptor.shader,
+            pushConstants: descriptor.pushConstants,
+            specialization: descriptor.specialization,
+            patchControlPoints: descriptor.patchControlPoints,
+        }
+    }
+    pub(crate) fn create(
+        self,
+        device: &Arc<crate::Device>,
+        target: &PipelineTarget,
+    ) -> Result<Pipeline, Box<dyn std::error::Error>> {
+        self.rasterization.checkFeatures(device.enabledFeatures())?;
+
+        let (renderpass, colorAttachmentCount, renderingCreateInfo) = match target {
+            PipelineTarget::Renderpass(renderpass, colorAttachmentCount) => {
+                (*renderpass, *colorAttachmentCount as usize, None)
+            }
+            PipelineTarget::Dynamic {
+                colorFormats,
+                depthFormat,
+            } => {
+                if device.dynamicRendering().is_none() {
+                    return Err(Box::new(PipelineCreateError::DynamicRenderingUnsupported));
+                }
+
+                let depthFormat = depthFormat.unwrap_or(vk::Format::UNDEFINED);
+
+                let renderingCreateInfo = PipelineRenderingCreateInfoKHR {
+                    s_type: STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR,
+                    p_next: std::ptr::null(),
+                    view_mask: 0,
+                    color_attachment_count: colorFormats.len() as u32,
+                    p_color_attachment_formats: colorFormats.as_ptr(),
+                    depth_attachment_format: depthFormat,
+                    stencil_attachment_format: if hasStencil(depthFormat) {
+                        depthFormat
+                    } else {
+                        vk::Format::UNDEFINED
+                    },
+                };
+
+                (vk::RenderPass::null(), colorFormats.len(), Some(renderingCreateInfo))
+            }
+        };
+
+        // Hold on to the modules, the shader may be reloaded while the pipeline is being created.
+        let modules = self.shader.modules();
+
+        let features = device.enabledFeatures();
+
+        if modules.hasTessellation() && features.tessellation_shader != vk::TRUE {
+            return Err(Box::new(PipelineCreateError::FeatureNotEnabled("tessellationShader")));
+        }
+        if modules.hasGeometry() && features.geometry_shader != vk::TRUE {
+            return Err(Box::new(PipelineCreateError::FeatureNotEnabled("geometryShader")));
+        }
+
+        match (self.topology, modules.hasTessellation()) {
+            (PrimitiveTopology::Patch, false) => {
+                return Err(Box::new(PipelineCreateError::MissingTessellationStages(
+                    self.shader.name.to_owned(),
+                )));
+            }
+            (topology, true) if topology != PrimitiveTopology::Patch => {
+                return Err(Box::new(PipelineCreateError::PatchTopologyRequired(
+                    self.shader.name.to_owned(),
+                )));
+            }
+            _ => {}
+        }
+
+        let stages = modules.stages();
+
+        let reflections: Vec<_> = stages
+            .iter()
+            .map(|&(stage, info)| (stage, &info.reflectionData))
+            .collect();
+
+        let pushConstantLayout = PushConstantLayout::reflect(&reflections)?;
+
+        validatePushConstants(&self.shader.name, pushConstantLayout.as_ref(), self.pushConstants.as_ref())?;
+
+        let specializationConstants: Vec<_> = stages
+            .iter()
+            .map(|(_, info)| info.reflectionData.specializationConstants())
+            .collect();
+
+        self.specialization
+            .validate(&self.shader.name, &specializationConstants)?;
+
+        let topology = match self.topology {
             PrimitiveTopology::Point => vk::PrimitiveTopology::POINT_LIST,
             PrimitiveTopology::Triangle => vk::PrimitiveTopology::TRIANGLE_LIST,
             PrimitiveTopology::Line => vk::PrimitiveTopology::LINE_LIST,
+            PrimitiveTopology::Patch => vk::PrimitiveTopology::PATCH_LIST,
         };
 
+        // The control shader's output vertex count is the output patch size, it says nothing about the input.
+        let patchControlPoints = if modules.hasTessellation() {
+            let patchControlPoints = self
+                .patchControlPoints
+                .ok_or_else(|| PipelineCreateError::PatchControlPointsRequired(self.shader.name.to_owned()))?;
+
+            let maxPatchSize = device.limits().max_tessellation_patch_size;
+
+            if !(1..=maxPatchSize).contains(&patchControlPoints) {
+                return Err(Box::new(PipelineCreateError::InvalidPatchControlPoints(
+                    patchControlPoints,
+                    maxPatchSize,
+                )));
+            }
+
+            patchControlPoints
+        } else {
+            0
+        };
+
+        let tessellationStateCreateInfo = vk::PipelineTessellationStateCreateInfo::builder()
+            .patch_control_points(patchControlPoints)
+            .build();
+
         let vertexInputStateCreateInfo = vk::PipelineVertexInputStateCreateInfo::builder().build();
 
         let inputAssemblyCreateInfo = vk::PipelineInputAssemblyStateCreateInfo::builder()
@@ -81,94 +621,93 @@ impl PipelineCreateInfo {
             .topology(topology)
             .build();
 
-        let viewport = vk::Viewport::builder().build();
+        let viewports = [vk::Viewport::builder().build()];
 
-        let scissor = vk::Rect2D::builder()
+        let scissors = [vk::Rect2D::builder()
             // .offset(vk::Offset2D::default())
             // .extent(vk::Extent2D::builder().width(width).height(height).build())
-            .build();
+            .build()];
 
         let viewportStateCreateInfo = vk::PipelineViewportStateCreateInfo::builder()
-            .viewports(&[viewport])
-            .scissors(&[scissor])
+            .viewports(&viewports)
+            .scissors(&scissors)
             .build();
 
+        let rasterization = &self.rasterization;
+
+        let depthBias = rasterization.depthBias.unwrap_or_default();
+
         let rasterizationStateCreateInfo = vk::PipelineRasterizationStateCreateInfo::builder()
-            .depth_clamp_enable(false)
+            .depth_clamp_enable(rasterization.depthClamp)
             .rasterizer_discard_enable(false)
-            .line_width(1.0)
-            .cull_mode(vk::CullModeFlags::BACK)
-            .polygon_mode(vk::PolygonMode::FILL)
-            .front_face(vk::FrontFace::CLOCKWISE)
-            .depth_bias_enable(false)
+            .line_width(rasterization.lineWidth)
+            .cull_mode(rasterization.cullMode.into())
+            .polygon_mode(rasterization.polygonMode.into())
+            .front_face(rasterization.frontFace.into())
+            .depth_bias_enable(rasterization.depthBias.is_some())
+            .depth_bias_constant_factor(depthBias.constantFactor)
+            .depth_bias_slope_factor(depthBias.slopeFactor)
+            .depth_bias_clamp(depthBias.clamp)
             .build();
 
         let multisampleStateCreateInfo = vk::PipelineMultisampleStateCreateInfo::builder()
-            .sample_shading_enable(descriptor.msaa)
+            .sample_shading_enable(self.msaa)
             .rasterization_samples(SampleCountFlags::TYPE_1)
             .build();
 
-        let attachment = vk::PipelineColorBlendAttachmentState::builder()
-            .color_write_mask(ColorComponentFlags::all())
-            .blend_enable(false)
-            .build();
+        let attachments = vec![
+            vk::PipelineColorBlendAttachmentState::builder()
+                .color_write_mask(ColorComponentFlags::all())
+                .blend_enable(false)
+                .build();
+            colorAttachmentCount
+        ];
 
         let colorBlendStateCreateInfo = vk::PipelineColorBlendStateCreateInfo::builder()
-            .attachments(&[attachment])
+            .attachments(&attachments)
             .build();
 
-        let pipelineLayout = vk::PipelineLayoutCreateInfo::builder().build();
+        // Line width is part of the rasterization state, so it isn't dynamic.
+        let dynamicStates = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
 
-        //let pipelineLayout = unsafe {device.rawDevice().create_pipeline_layout(&&pipelineLayout, None).unwrap()};
+        let dynamicStateCreateInfo = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamicStates)
+            .build();
 
-        let shaderStageCreateInfos = vec![
-            vk::PipelineShaderStageCreateInfo::builder()
-                .stage(vk::ShaderStageFlags::VERTEX)
-                .module(descriptor.shader.vertex.module)
-                .name(
-                    CString::new(descriptor.shader.vertex.compileInfo.entryPoint.as_str())
-                        .unwrap()
-                        .as_c_str(),
-                )
-                .build(),
-            vk::PipelineShaderStageCreateInfo::builder()
-                .stage(vk::ShaderStageFlags::FRAGMENT)
-                .module(descriptor.shader.fragment.module)
-                .name(
-                    CString::new(descriptor.shader.fragment.compileInfo.entryPoint.as_str())
-                        .unwrap()
-                        .as_c_str(),
+        // The stage create infos point into these, so they have to outlive pipeline creation.
+        let entryPoints = stages
+            .iter()
+            .map(|(_, info)| CString::new(info.compileInfo.entryPoint.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let specializations: Vec<_> = specializationConstants
+            .iter()
+            .map(|constants| StageSpecialization::new(&self.specialization, constants))
+            .collect();
+
+        let specializationInfos: Vec<_> = specializations
+            .iter()
+            .map(|specialization| specialization.info())
+            .collect();
+
+        let shaderStageCreateInfos: Vec<_> = stages
+            .iter()
+            .enumerate()
+            .map(|(i, &(stage, info))| {
+                shaderStageCreateInfo(
+                    stage,
+                    info,
+                    &entryPoints[i],
+                    specializationInfos[i].as_ref(),
                 )
-                .build(),
-        ];
+            })
+            .collect();
 
-        let dynamicStateCreateInfo = vk::PipelineDynamicStateCreateInfo::builder()
-            .dynamic_states(&[
-                vk::DynamicState::VIEWPORT,
-                vk::DynamicState::LINE_WIDTH,
-                vk::DynamicState::SCISSOR,
-            ])
-            .build();
+        let pushConstantRanges: Vec<_> = pushConstantLayout.iter().map(|layout| layout.range()).collect();
 
-        Self {
-            vertexInputStateCreateInfo,
-            inputAssemblyCreateInfo,
-            viewportStateCreateInfo,
-            rasterizationStateCreateInfo,
-            multisampleStateCreateInfo,
-            colorBlendStateCreateInfo,
-
-            shaderStageCreateInfos,
-            dynamicStateCreateInfo,
-            shader: descriptor.shader,
-        }
-    }
-    pub(crate) fn create(
-        self,
-        device: &Arc<crate::Device>,
-        renderpass: vk::RenderPass,
-    ) -> Result<Pipeline, Box<dyn std::error::Error>> {
-        let layoutCreateInfo = vk::PipelineLayoutCreateInfo::builder().build();
+        let layoutCreateInfo = vk::PipelineLayoutCreateInfo::builder()
+            .push_constant_ranges(&pushConstantRanges)
+            .build();
 
         let pipelineLayout = unsafe {
             device
@@ -176,35 +715,52 @@ impl PipelineCreateInfo {
                 .create_pipeline_layout(&layoutCreateInfo, None)?
         };
 
-        let graphicsPipelineCreateInfo = vk::GraphicsPipelineCreateInfo::builder()
-            .vertex_input_state(&self.vertexInputStateCreateInfo)
-            .input_assembly_state(&self.inputAssemblyCreateInfo)
-            .viewport_state(&self.viewportStateCreateInfo)
-            .rasterization_state(&self.rasterizationStateCreateInfo)
-            .multisample_state(&self.multisampleStateCreateInfo)
-            .dynamic_state(&self.dynamicStateCreateInfo)
-            .color_blend_state(&self.colorBlendStateCreateInfo)
+        let mut graphicsPipelineCreateInfo = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shaderStageCreateInfos)
+            .vertex_input_state(&vertexInputStateCreateInfo)
+            .input_assembly_state(&inputAssemblyCreateInfo)
+            .viewport_state(&viewportStateCreateInfo)
+            .rasterization_state(&rasterizationStateCreateInfo)
+            .multisample_state(&multisampleStateCreateInfo)
+            .dynamic_state(&dynamicStateCreateInfo)
+            .color_blend_state(&colorBlendStateCreateInfo)
             .layout(pipelineLayout)
             .render_pass(renderpass)
             .subpass(0)
             .build();
 
+        if modules.hasTessellation() {
+            graphicsPipelineCreateInfo.p_tessellation_state = &tessellationStateCreateInfo;
+        }
+
+        if let Some(renderingCreateInfo) = &renderingCreateInfo {
+            graphicsPipelineCreateInfo.p_next = renderingCreateInfo as *const _ as *const c_void;
+        }
+
         let pipeline = unsafe {
-            device
-                .raw()
-                .create_graphics_pipelines(
-                    vk::PipelineCache::null(),
-                    &[graphicsPipelineCreateInfo],
-                    None,
-                )
-                .unwrap()
+            device.raw().create_graphics_pipelines(
+                vk::PipelineCache::null(),
+                &[graphicsPipelineCreateInfo],
+                None,
+            )
+        };
+
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline[0],
+            Err((_, err)) => {
+                unsafe { device.raw().destroy_pipeline_layout(pipelineLayout, None) };
+                return Err(Box::new(err));
+            }
         };
 
         Ok(Pipeline {
             device: device.clone(),
             createInfo: self,
-            pipeline: pipeline[0],
+            target: target.clone(),
+            shaderGeneration: modules.generation(),
+            pipeline,
             layout: pipelineLayout,
+            pushConstantLayout,
         })
     }
     // pub fn build(self) -> Result<Pipeline, Box<dyn Error>> {
@@ -214,6 +770,231 @@ impl PipelineCreateInfo {
     //     todo!()
     // }
 }
+pub struct ComputePipeline {
+    device: Arc<crate::Device>,
+    shader: Arc<ComputeShader>,
+    pipeline: vk::Pipeline,
+    layout: vk::PipelineLayout,
+    setLayouts: Vec<vk::DescriptorSetLayout>,
+    pushConstantLayout: Option<PushConstantLayout>,
+    pushConstants: Option<PushConstantsInfo>,
+    specialization: Specialization,
+    shaderGeneration: u64,
+    workgroupSize: [u32; 3],
+}
+impl ComputePipeline {
+    /// `pushConstants` declares the struct used to upload push constants, e.g. `PushConstantsInfo::of::<T>()`;
+    /// like `PipelineDesciptor::pushConstants`, its layout is checked here rather than on every upload.
+    pub fn create(
+        device: &Arc<crate::Device>,
+        shader: Arc<ComputeShader>,
+        specialization: &Specialization,
+        pushConstants: Option<PushConstantsInfo>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Hold on to the module, the shader may be reloaded while the pipeline is being created.
+        let module = shader.module();
+        let reflectionData = &module.compute.reflectionData;
+
+        specialization.validate(&shader.name, &[reflectionData.specializationConstants()])?;
+
+        let workgroupSize = Self::specializedWorkgroupSize(reflectionData, specialization);
+
+        let pushConstantLayout =
+            PushConstantLayout::reflect(&[(vk::ShaderStageFlags::COMPUTE, reflectionData)])?;
+
+        validatePushConstants(&shader.name, pushConstantLayout.as_ref(), pushConstants.as_ref())?;
+
+        let pushConstantRanges: Vec<_> = pushConstantLayout.iter().map(|layout| layout.range()).collect();
+
+        // Everything that can fail without Vulkan objects to clean up comes first.
+        let entryPoint = CString::new(module.compute.compileInfo.entryPoint.as_str())?;
+
+        let setLayouts = createSetLayouts(device, vk::ShaderStageFlags::COMPUTE, reflectionData.bindings())?;
+
+        let layoutCreateInfo = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&setLayouts)
+            .push_constant_ranges(&pushConstantRanges)
+            .build();
+
+        let layout = match unsafe { device.raw().create_pipeline_layout(&layoutCreateInfo, None) } {
+            Ok(layout) => layout,
+            Err(err) => {
+                destroySetLayouts(device, &setLayouts);
+                return Err(Box::new(err));
+            }
+        };
+
+        let stageSpecialization =
+            StageSpecialization::new(specialization, reflectionData.specializationConstants());
+        let specializationInfo = stageSpecialization.info();
+
+        let stageCreateInfo = shaderStageCreateInfo(
+            vk::ShaderStageFlags::COMPUTE,
+            &module.compute,
+            &entryPoint,
+            specializationInfo.as_ref(),
+        );
+
+        let computePipelineCreateInfo = vk::ComputePipelineCreateInfo::builder()
+            .stage(stageCreateInfo)
+            .layout(layout)
+            .build();
+
+        let pipeline = unsafe {
+            device.raw().create_compute_pipelines(
+                vk::PipelineCache::null(),
+                &[computePipelineCreateInfo],
+                None,
+            )
+        };
+
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline[0],
+            Err((_, err)) => {
+                unsafe { device.raw().destroy_pipeline_layout(layout, None) };
+                destroySetLayouts(device, &setLayouts);
+                return Err(Box::new(err));
+            }
+        };
+
+        Ok(Self {
+            device: device.clone(),
+            shader,
+            pipeline,
+            layout,
+            setLayouts,
+            pushConstantLayout,
+            pushConstants,
+            specialization: specialization.clone(),
+            shaderGeneration: module.generation(),
+            workgroupSize,
+        })
+    }
+    /// True once the shader has been reloaded since this pipeline was created.
+    pub fn isOutdated(&self) -> bool {
+        self.shader.generation() != self.shaderGeneration
+    }
+    /// Creates the same pipeline again from the shader's current module.
+    ///
+    /// Compute pipelines aren't cached, so nothing rebuilds them when the shader is reloaded; replace outdated
+    /// ones with the result of this.
+    pub fn rebuild(&self) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::create(&self.device, self.shader.clone(), &self.specialization, self.pushConstants.clone())
+    }
+    /// Workgroup size after applying any `local_size_*_id` specialization.
+    fn specializedWorkgroupSize(reflectionData: &ReflectionData, specialization: &Specialization) -> [u32; 3] {
+        let mut workgroupSize = reflectionData.workgroupSize().unwrap_or([1, 1, 1]);
+
+        for (size, specId) in workgroupSize
+            .iter_mut()
+            .zip(reflectionData.workgroupSizeSpecIds().iter())
+        {
+            let value = specId.and_then(|id| {
+                reflectionData
+                    .specializationConstants()
+                    .iter()
+                    .find(|constant| constant.id() == id)
+                    .and_then(|constant| specialization.valueOf(constant))
+            });
+
+            if let Some(SpecializationValue::UInt(value)) = value {
+                *size = value;
+            }
+        }
+
+        workgroupSize
+    }
+    pub fn shader(&self) -> &Arc<ComputeShader> {
+        &self.shader
+    }
+    pub fn workgroupSize(&self) -> [u32; 3] {
+        self.workgroupSize
+    }
+    /// Layouts of the descriptor sets the shader binds, indexed by set number.
+    pub fn descriptorSetLayouts(&self) -> &[vk::DescriptorSetLayout] {
+        &self.setLayouts
+    }
+    /// Binds descriptor sets allocated from `descriptorSetLayouts`, starting at set `firstSet`.
+    pub fn bindDescriptorSets(
+        &self,
+        commandBuffer: &CommandBuffer,
+        firstSet: u32,
+        descriptorSets: &[vk::DescriptorSet],
+    ) {
+        unsafe {
+            self.device.raw().cmd_bind_descriptor_sets(
+                commandBuffer.raw(),
+                vk::PipelineBindPoint::COMPUTE,
+                self.layout,
+                firstSet,
+                descriptorSets,
+                &[],
+            );
+        }
+    }
+    pub fn pushConstantLayout(&self) -> Option<&PushConstantLayout> {
+        self.pushConstantLayout.as_ref()
+    }
+    fn requirePushConstantLayout(&self) -> Result<&PushConstantLayout, PushConstantError> {
+        self.pushConstantLayout
+            .as_ref()
+            .ok_or_else(|| PushConstantError::NoPushConstants(self.shader.name.to_owned()))
+    }
+    pub fn pushConstants<T: PushConstants>(
+        &self,
+        commandBuffer: &CommandBuffer,
+        data: &T,
+    ) -> Result<(), PushConstantError> {
+        let validatedType = self.pushConstants.as_ref().map(|info| info.typeId());
+
+        self.requirePushConstantLayout()?
+            .push(&self.device, commandBuffer, self.layout, validatedType, data)
+    }
+    pub fn setPushConstant<T: PushConstantValue>(
+        &self,
+        commandBuffer: &CommandBuffer,
+        name: &str,
+        value: &T,
+    ) -> Result<(), PushConstantError> {
+        self.requirePushConstantLayout()?
+            .pushMember(&self.device, commandBuffer, self.layout, name, value)
+    }
+    pub fn bind(&self, commandBuffer: &CommandBuffer) {
+        unsafe {
+            self.device.raw().cmd_bind_pipeline(
+                commandBuffer.raw(),
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline,
+            );
+        }
+    }
+    /// Binds the pipeline and dispatches the given number of workgroups.
+    pub fn dispatch(&self, commandBuffer: &CommandBuffer, groupsX: u32, groupsY: u32, groupsZ: u32) {
+        self.bind(commandBuffer);
+
+        unsafe {
+            self.device
+                .raw()
+                .cmd_dispatch(commandBuffer.raw(), groupsX, groupsY, groupsZ);
+        }
+    }
+    /// Like `dispatch`, but takes an invocation count per dimension and rounds it up to whole workgroups.
+    pub fn dispatchInvocations(&self, commandBuffer: &CommandBuffer, width: u32, height: u32, depth: u32) {
+        let [x, y, z] = self.workgroupSize();
+
+        self.dispatch(commandBuffer, width.div_ceil(x), height.div_ceil(y), depth.div_ceil(z));
+    }
+}
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.raw().destroy_pipeline_layout(self.layout, None);
+            self.device.raw().destroy_pipeline(self.pipeline, None);
+        }
+
+        destroySetLayouts(&self.device, &self.setLayouts);
+    }
+}
 impl PipelineCreateInfo {
     // pub fn create() -> Result<Pipeline, Box<dyn Error>> {
     //     let x = vk::PipelineVertexInputStateCreateInfo::builder();
@@ -0,0 +1,202 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread::JoinHandle,
+};
+
+use super::{
+    cache::PipelineCache,
+    pipeline::{Pipeline, PipelineDesciptor, PipelineTarget},
+};
+
+/// What a `PipelineHandle` yields while its pipeline is still being compiled.
+#[derive(Clone, Default)]
+pub enum PipelineFallback {
+    /// Don't draw until the pipeline is ready.
+    #[default]
+    Skip,
+    /// Draw with another pipeline in the meantime.
+    Pipeline(Arc<Pipeline>),
+}
+
+enum PipelineState {
+    Pending,
+    Ready(Arc<Pipeline>),
+    Failed(String),
+}
+
+struct HandleInner {
+    state: Mutex<PipelineState>,
+    finished: Condvar,
+}
+
+/// A pipeline that becomes available once a worker thread has compiled it.
+#[derive(Clone)]
+pub struct PipelineHandle {
+    inner: Arc<HandleInner>,
+    fallback: PipelineFallback,
+}
+impl PipelineHandle {
+    fn new(state: PipelineState, fallback: PipelineFallback) -> Self {
+        Self {
+            inner: Arc::new(HandleInner {
+                state: Mutex::new(state),
+                finished: Condvar::new(),
+            }),
+            fallback,
+        }
+    }
+    fn finish(&self, state: PipelineState) {
+        *self.inner.state.lock().unwrap() = state;
+        self.inner.finished.notify_all();
+    }
+    pub fn isReady(&self) -> bool {
+        matches!(*self.inner.state.lock().unwrap(), PipelineState::Ready(_))
+    }
+    pub fn error(&self) -> Option<String> {
+        match &*self.inner.state.lock().unwrap() {
+            PipelineState::Failed(err) => Some(err.to_owned()),
+            _ => None,
+        }
+    }
+    /// The compiled pipeline, or the fallback if it isn't ready (or failed to compile).
+    ///
+    /// `None` means the draw should be skipped.
+    pub fn get(&self) -> Option<Arc<Pipeline>> {
+        match &*self.inner.state.lock().unwrap() {
+            PipelineState::Ready(pipeline) => Some(pipeline.clone()),
+            _ => match &self.fallback {
+                PipelineFallback::Skip => None,
+                PipelineFallback::Pipeline(pipeline) => Some(pipeline.clone()),
+            },
+        }
+    }
+    /// Blocks until the pipeline has been compiled.
+    pub fn wait(&self) -> Result<Arc<Pipeline>, String> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        loop {
+            match &*state {
+                PipelineState::Pending => state = self.inner.finished.wait(state).unwrap(),
+                PipelineState::Ready(pipeline) => return Ok(pipeline.clone()),
+                PipelineState::Failed(err) => return Err(err.to_owned()),
+            }
+        }
+    }
+}
+
+struct Job {
+    descriptor: PipelineDesciptor,
+    target: PipelineTarget,
+    handle: PipelineHandle,
+}
+
+/// Builds pipelines on worker threads so that new materials don't stall the render thread.
+pub struct PipelineCompiler {
+    cache: Arc<PipelineCache>,
+    fallback: PipelineFallback,
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+impl PipelineCompiler {
+    pub fn new(cache: &Arc<PipelineCache>, workerCount: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..workerCount.max(1))
+            .map(|i| {
+                let cache = cache.clone();
+                let receiver = receiver.clone();
+
+                std::thread::Builder::new()
+                    .name(format!("PipelineCompiler{}", i))
+                    .spawn(move || Self::work(&cache, &receiver))
+                    .expect("Failed to spawn pipeline compiler thread")
+            })
+            .collect();
+
+        Self {
+            cache: cache.clone(),
+            fallback: PipelineFallback::default(),
+            sender: Some(sender),
+            workers,
+        }
+    }
+    fn work(cache: &PipelineCache, receiver: &Mutex<mpsc::Receiver<Job>>) {
+        loop {
+            let job = match receiver.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+
+            // A panicking job would otherwise leave its handle pending forever and take the worker down with it.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| cache.get(&job.descriptor, &job.target)));
+
+            let state = match result {
+                Ok(Ok(pipeline)) => PipelineState::Ready(pipeline),
+                Ok(Err(err)) => {
+                    log::error!("Failed to compile pipeline: {}", err);
+                    PipelineState::Failed(err.to_string())
+                }
+                Err(payload) => {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+
+                    log::error!("Pipeline compilation panicked: {}", message);
+                    PipelineState::Failed(format!("Pipeline compilation panicked: {}", message))
+                }
+            };
+
+            job.handle.finish(state);
+        }
+    }
+    /// Fallback given to handles requested from now on.
+    pub fn setFallback(&mut self, fallback: PipelineFallback) -> &mut Self {
+        self.fallback = fallback;
+
+        self
+    }
+    pub fn cache(&self) -> &Arc<PipelineCache> {
+        &self.cache
+    }
+    /// Queues a pipeline for compilation; already cached pipelines are returned ready.
+    ///
+    /// Cached pipelines whose shader was reloaded are queued for a rebuild, and stand in as the fallback until
+    /// it's done.
+    pub fn request(&self, descriptor: PipelineDesciptor, target: PipelineTarget) -> PipelineHandle {
+        let fallback = match self.cache.cached(&descriptor, &target) {
+            Some(pipeline) if !pipeline.isOutdated() => {
+                return PipelineHandle::new(PipelineState::Ready(pipeline), self.fallback.clone());
+            }
+            Some(outdated) => PipelineFallback::Pipeline(outdated),
+            None => self.fallback.clone(),
+        };
+
+        let handle = PipelineHandle::new(PipelineState::Pending, fallback);
+
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(Job {
+                descriptor,
+                target,
+                handle: handle.clone(),
+            })
+            .expect("Pipeline compiler threads have shut down");
+
+        handle
+    }
+}
+impl Drop for PipelineCompiler {
+    fn drop(&mut self) {
+        // Closing the channel lets the workers finish the queued jobs and exit.
+        self.sender.take();
+
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{any::TypeId, collections::HashMap};
+
+use ash::{version::DeviceV1_0, vk};
+
+use crate::{
+    render::shader::reflection::{BlockMember, ReflectionData},
+    CommandBuffer,
+};
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum PushConstantError {
+    #[error("Pipeline using shader {0:?} has no push constants!")]
+    NoPushConstants(String),
+    #[error("Push constant member {0:?} is declared with offset {1} in one stage and {2} in another!")]
+    StageMismatch(String, u32, u32),
+    #[error("Push constant member {0:?} was not found in the shader!")]
+    UnknownMember(String),
+    #[error("Push constant member {0:?} is {1} bytes, but a value of {2} bytes was provided!")]
+    SizeMismatch(String, u32, u32),
+    #[error("{0}: no field corresponding to push constant member {1:?}!")]
+    MissingField(&'static str, String),
+    #[error("{0}: field {1:?} is at offset {2} with size {3}, but the shader expects offset {4} with size {5}!")]
+    FieldMismatch(&'static str, String, u32, u32, u32, u32),
+    #[error("{0}: struct is {1} bytes, but the shader's push constant range ends at {2}!")]
+    StructTooSmall(&'static str, u32, u32),
+}
+
+#[derive(Clone)]
+pub struct PushConstantField {
+    pub name: &'static str,
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// A `#[repr(C)]` struct that can be uploaded as push constants.
+///
+/// Implement with the `pushConstants!` macro, which derives the field offsets from the struct itself;
+/// field names have to match the member names of the shader's push constant block.
+///
+/// # Safety
+///
+/// Uploads read `size_of::<Self>()` bytes straight from the value, so the type must be `#[repr(C)]`, contain
+/// no pointers, references or padding, and `fields` must report each field's true offset and size within it.
+pub unsafe trait PushConstants: Copy + 'static {
+    fn fields() -> Vec<PushConstantField>;
+}
+
+/// Implements [`PushConstants`] for a `#[repr(C)]` struct, listing every field with its type:
+///
+/// ```ignore
+/// pushConstants!(unsafe Transform { scale: f32, offset: [f32; 2], tint: [f32; 4] });
+/// ```
+///
+/// The `unsafe` stands for the `#[repr(C)]` requirement, which the macro can't check. Padding is rejected at
+/// compile time: the listed field sizes have to add up to the size of the struct, so every field has to be
+/// listed and the field types themselves must not contain padding.
+#[macro_export]
+macro_rules! pushConstants {
+    (unsafe $ty:ty { $($field:ident : $fieldTy:ty),* $(,)? }) => {
+        const _: () = assert!(
+            std::mem::size_of::<$ty>() == 0 $(+ std::mem::size_of::<$fieldTy>())*,
+            concat!(stringify!($ty), " has padding or unlisted fields"),
+        );
+
+        unsafe impl $crate::render::graph::PushConstants for $ty {
+            fn fields() -> Vec<$crate::render::graph::PushConstantField> {
+                // Makes sure the listed types are the fields' actual types.
+                let _ = |value: &$ty| { $(let _: &$fieldTy = &value.$field;)* };
+
+                let uninit = std::mem::MaybeUninit::<$ty>::uninit();
+                let base = uninit.as_ptr();
+
+                vec![$({
+                    let field = unsafe { std::ptr::addr_of!((*base).$field) };
+
+                    $crate::render::graph::PushConstantField {
+                        name: stringify!($field),
+                        offset: (field as usize - base as usize) as u32,
+                        size: std::mem::size_of::<$fieldTy>() as u32,
+                    }
+                }),*]
+            }
+        }
+    };
+}
+
+/// A value without padding that can be uploaded as a single push constant member.
+///
+/// # Safety
+///
+/// Every byte of the value must be initialized, so the type must not contain padding.
+pub unsafe trait PushConstantValue: Copy + 'static {}
+
+unsafe impl PushConstantValue for f32 {}
+unsafe impl PushConstantValue for f64 {}
+unsafe impl PushConstantValue for i32 {}
+unsafe impl PushConstantValue for u32 {}
+unsafe impl PushConstantValue for i64 {}
+unsafe impl PushConstantValue for u64 {}
+unsafe impl<T: PushConstantValue, const N: usize> PushConstantValue for [T; N] {}
+
+/// Layout of a push constant struct, captured so it can be checked against the shader.
+#[derive(Clone)]
+pub struct PushConstantsInfo {
+    typeId: TypeId,
+    typeName: &'static str,
+    size: u32,
+    fields: Vec<PushConstantField>,
+}
+impl PushConstantsInfo {
+    pub fn of<T: PushConstants>() -> Self {
+        Self {
+            typeId: TypeId::of::<T>(),
+            typeName: std::any::type_name::<T>(),
+            size: std::mem::size_of::<T>() as u32,
+            fields: T::fields(),
+        }
+    }
+    pub(crate) fn typeId(&self) -> TypeId {
+        self.typeId
+    }
+}
+
+/// Push constant members of every stage of a pipeline, merged into a single range.
+pub struct PushConstantLayout {
+    stages: vk::ShaderStageFlags,
+    offset: u32,
+    size: u32,
+    members: HashMap<String, BlockMember>,
+}
+impl PushConstantLayout {
+    pub(crate) fn reflect(
+        stages: &[(vk::ShaderStageFlags, &ReflectionData)],
+    ) -> Result<Option<Self>, PushConstantError> {
+        let mut layout: Option<Self> = None;
+
+        for &(stage, reflectionData) in stages {
+            let block = match reflectionData.pushConstants() {
+                Some(block) => block,
+                None => continue,
+            };
+
+            let (offset, size) = block.range();
+
+            let layout = layout.get_or_insert_with(|| Self {
+                stages: vk::ShaderStageFlags::empty(),
+                offset,
+                size: 0,
+                members: HashMap::new(),
+            });
+
+            let end = (layout.offset + layout.size).max(offset + size);
+            layout.offset = layout.offset.min(offset);
+            layout.size = end - layout.offset;
+            layout.stages |= stage;
+
+            for member in block.members() {
+                match layout.members.get(member.name()) {
+                    Some(existing) if existing.offset() != member.offset() => {
+                        return Err(PushConstantError::StageMismatch(
+                            member.name().to_owned(),
+                            existing.offset(),
+                            member.offset(),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        layout
+                            .members
+                            .insert(member.name().to_owned(), member.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(layout)
+    }
+    pub fn stages(&self) -> vk::ShaderStageFlags {
+        self.stages
+    }
+    pub fn members(&self) -> &HashMap<String, BlockMember> {
+        &self.members
+    }
+    pub(crate) fn range(&self) -> vk::PushConstantRange {
+        vk::PushConstantRange::builder()
+            .stage_flags(self.stages)
+            .offset(self.offset)
+            .size(self.size)
+            .build()
+    }
+    pub(crate) fn validate(&self, info: &PushConstantsInfo) -> Result<(), PushConstantError> {
+        for (name, member) in &self.members {
+            let field = info
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .ok_or_else(|| PushConstantError::MissingField(info.typeName, name.to_owned()))?;
+
+            if field.offset != member.offset() || field.size != member.size() {
+                return Err(PushConstantError::FieldMismatch(
+                    info.typeName,
+                    name.to_owned(),
+                    field.offset,
+                    field.size,
+                    member.offset(),
+                    member.size(),
+                ));
+            }
+        }
+
+        if info.size < self.offset + self.size {
+            return Err(PushConstantError::StructTooSmall(
+                info.typeName,
+                info.size,
+                self.offset + self.size,
+            ));
+        }
+
+        Ok(())
+    }
+    /// Uploads the whole push constant range from `data`.
+    ///
+    /// `validatedType` is the type that was already checked when the pipeline was created; any other
+    /// type gets validated here.
+    pub(crate) fn push<T: PushConstants>(
+        &self,
+        device: &crate::Device,
+        commandBuffer: &CommandBuffer,
+        pipelineLayout: vk::PipelineLayout,
+        validatedType: Option<TypeId>,
+        data: &T,
+    ) -> Result<(), PushConstantError> {
+        if validatedType != Some(TypeId::of::<T>()) {
+            self.validate(&PushConstantsInfo::of::<T>())?;
+        }
+
+        let bytes = bytesOf(data);
+        let range = self.offset as usize..(self.offset + self.size) as usize;
+
+        self.record(device, commandBuffer, pipelineLayout, self.offset, &bytes[range]);
+
+        Ok(())
+    }
+    /// Uploads a single member by name.
+    pub(crate) fn pushMember<T: PushConstantValue>(
+        &self,
+        device: &crate::Device,
+        commandBuffer: &CommandBuffer,
+        pipelineLayout: vk::PipelineLayout,
+        name: &str,
+        value: &T,
+    ) -> Result<(), PushConstantError> {
+        let member = self
+            .members
+            .get(name)
+            .ok_or_else(|| PushConstantError::UnknownMember(name.to_owned()))?;
+
+        let size = std::mem::size_of::<T>() as u32;
+        if size != member.size() {
+            return Err(PushConstantError::SizeMismatch(
+                name.to_owned(),
+                member.size(),
+                size,
+            ));
+        }
+
+        self.record(
+            device,
+            commandBuffer,
+            pipelineLayout,
+            member.offset(),
+            bytesOf(value),
+        );
+
+        Ok(())
+    }
+    fn record(
+        &self,
+        device: &crate::Device,
+        commandBuffer: &CommandBuffer,
+        pipelineLayout: vk::PipelineLayout,
+        offset: u32,
+        bytes: &[u8],
+    ) {
+        unsafe {
+            device.raw().cmd_push_constants(
+                commandBuffer.raw(),
+                pipelineLayout,
+                self.stages,
+                offset,
+                bytes,
+            );
+        }
+    }
+}
+
+/// Only sound for types without padding, which both `PushConstants` and `PushConstantValue` guarantee.
+fn bytesOf<T: Copy>(value: &T) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>())
+    }
+}
@@ -150,13 +150,13 @@ impl RenderpassBuilder {
         Ok(self)
     }
     fn checkUniformValidity(self) -> Result<Self, RenderpassValidationError> {
-        let fragment = &self.pipeline.shader.fragment;
+        let modules = self.pipeline.shader.modules();
+        let fragment = &modules.fragment;
 
         for (&name, colorInput) in &self.colorInputs {
             if fragment
                 .reflectionData
-                .samplers()
-                .get(colorInput.uniformName)
+                .bindingNamed(colorInput.uniformName)
                 .is_none()
             {
                 return Err(RenderpassValidationError::ShaderUniformNotFound(
@@ -187,8 +187,7 @@ impl RenderpassBuilder {
         if let Some(depthInput) = &self.depthInput {
             if fragment
                 .reflectionData
-                .samplers()
-                .get(depthInput.uniformName)
+                .bindingNamed(depthInput.uniformName)
                 .is_none()
             {
                 return Err(RenderpassValidationError::ShaderUniformNotFound(
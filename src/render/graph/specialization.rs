@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+};
+
+use ash::vk;
+
+use crate::render::shader::reflection::{SpecializationConstant, SpecializationType};
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum SpecializationError {
+    #[error("Specialization constant {0:?} was not found in shader {1:?}!")]
+    UnknownConstant(SpecializationKey, String),
+    #[error("Specialization constant {0:?} is declared as {1:?}, but a {2:?} value was provided!")]
+    TypeMismatch(SpecializationKey, SpecializationType, SpecializationType),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SpecializationValue {
+    Bool(bool),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    Double(f64),
+}
+impl SpecializationValue {
+    pub fn valueType(&self) -> SpecializationType {
+        match self {
+            Self::Bool(_) => SpecializationType::Bool,
+            Self::Int(_) => SpecializationType::Int,
+            Self::UInt(_) => SpecializationType::UInt,
+            Self::Float(_) => SpecializationType::Float,
+            Self::Double(_) => SpecializationType::Double,
+        }
+    }
+    fn bytes(&self) -> Vec<u8> {
+        match *self {
+            Self::Bool(x) => (x as vk::Bool32).to_ne_bytes().to_vec(),
+            Self::Int(x) => x.to_ne_bytes().to_vec(),
+            Self::UInt(x) => x.to_ne_bytes().to_vec(),
+            Self::Float(x) => x.to_ne_bytes().to_vec(),
+            Self::Double(x) => x.to_ne_bytes().to_vec(),
+        }
+    }
+}
+// Compare bit patterns so that values can key the pipeline cache.
+impl PartialEq for SpecializationValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.valueType() == other.valueType() && self.bytes() == other.bytes()
+    }
+}
+impl Eq for SpecializationValue {}
+impl Hash for SpecializationValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.valueType() as u32).hash(state);
+        self.bytes().hash(state);
+    }
+}
+impl From<bool> for SpecializationValue {
+    fn from(x: bool) -> Self {
+        Self::Bool(x)
+    }
+}
+impl From<i32> for SpecializationValue {
+    fn from(x: i32) -> Self {
+        Self::Int(x)
+    }
+}
+impl From<u32> for SpecializationValue {
+    fn from(x: u32) -> Self {
+        Self::UInt(x)
+    }
+}
+impl From<f32> for SpecializationValue {
+    fn from(x: f32) -> Self {
+        Self::Float(x)
+    }
+}
+impl From<f64> for SpecializationValue {
+    fn from(x: f64) -> Self {
+        Self::Double(x)
+    }
+}
+
+/// Specialization constants are addressed by the name of the `const` in the shader, or by their
+/// `constant_id` for unnamed ones such as `local_size_x_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SpecializationKey {
+    Name(String),
+    Id(u32),
+}
+impl SpecializationKey {
+    fn matches(&self, constant: &SpecializationConstant) -> bool {
+        match self {
+            Self::Name(name) => name == constant.name(),
+            Self::Id(id) => *id == constant.id(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Specialization {
+    values: BTreeMap<SpecializationKey, SpecializationValue>,
+}
+impl Specialization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set<T: Into<SpecializationValue>>(&mut self, name: &str, value: T) -> &mut Self {
+        self.values
+            .insert(SpecializationKey::Name(name.to_owned()), value.into());
+
+        self
+    }
+    pub fn setId<T: Into<SpecializationValue>>(&mut self, id: u32, value: T) -> &mut Self {
+        self.values.insert(SpecializationKey::Id(id), value.into());
+
+        self
+    }
+    pub fn isEmpty(&self) -> bool {
+        self.values.is_empty()
+    }
+    pub(crate) fn valueOf(&self, constant: &SpecializationConstant) -> Option<SpecializationValue> {
+        self.values
+            .iter()
+            .find(|(key, _)| key.matches(constant))
+            .map(|(_, &value)| value)
+    }
+    /// Checks that every value names a constant declared by one of the stages, with a matching type.
+    pub(crate) fn validate(
+        &self,
+        shaderName: &str,
+        stages: &[&[SpecializationConstant]],
+    ) -> Result<(), SpecializationError> {
+        for (key, value) in &self.values {
+            let constant = stages
+                .iter()
+                .flat_map(|constants| constants.iter())
+                .find(|constant| key.matches(constant))
+                .ok_or_else(|| SpecializationError::UnknownConstant(key.clone(), shaderName.to_owned()))?;
+
+            if constant.constantType() != value.valueType() {
+                return Err(SpecializationError::TypeMismatch(
+                    key.clone(),
+                    constant.constantType(),
+                    value.valueType(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Map entries and data for one stage; keep it alive until the pipeline has been created.
+pub(crate) struct StageSpecialization {
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+impl StageSpecialization {
+    pub(crate) fn new(specialization: &Specialization, constants: &[SpecializationConstant]) -> Self {
+        let mut entries = Vec::new();
+        let mut data = Vec::new();
+
+        for constant in constants {
+            if let Some(value) = specialization.valueOf(constant) {
+                let bytes = value.bytes();
+
+                entries.push(
+                    vk::SpecializationMapEntry::builder()
+                        .constant_id(constant.id())
+                        .offset(data.len() as u32)
+                        .size(bytes.len())
+                        .build(),
+                );
+                data.extend_from_slice(&bytes);
+            }
+        }
+
+        Self { entries, data }
+    }
+    pub(crate) fn info(&self) -> Option<vk::SpecializationInfo> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        Some(
+            vk::SpecializationInfo::builder()
+                .map_entries(&self.entries)
+                .data(&self.data)
+                .build(),
+        )
+    }
+}
@@ -107,17 +107,31 @@ pub struct Gfx {
     swapchain: super::Swapchain,
 }
 impl Gfx {
-    fn getExtensions(window: &Window) -> Vec<*const i8> {
+    /// Whether the Vulkan 1.0 instance can have VK_KHR_get_physical_device_properties2, which device extensions
+    /// such as VK_KHR_dynamic_rendering depend on.
+    fn supportsProperties2(entry: &Entry) -> Result<bool, Box<dyn Error>> {
+        let name = vk::KhrGetPhysicalDeviceProperties2Fn::name();
+
+        Ok(entry
+            .enumerate_instance_extension_properties()?
+            .iter()
+            .any(|prop| unsafe { CStr::from_ptr(prop.extension_name.as_ptr()) } == name))
+    }
+    fn getExtensions(window: &Window, properties2: bool) -> Vec<*const i8> {
         let mut baseExtensions = ash_window::enumerate_required_extensions(window).unwrap();
 
         #[cfg(debug_assertions)]
         baseExtensions.push(DebugUtils::name());
 
+        if properties2 {
+            baseExtensions.push(vk::KhrGetPhysicalDeviceProperties2Fn::name());
+        }
+
         println!("{:?}", baseExtensions);
 
         baseExtensions.iter().map(|x| x.as_ptr()).collect()
     }
-    fn createInstance(entry: &Entry, window: &Window) -> Result<Instance, Box<dyn Error>> {
+    fn createInstance(entry: &Entry, window: &Window, properties2: bool) -> Result<Instance, Box<dyn Error>> {
         unsafe {
             let appName = CString::new("Nuru").unwrap();
 
@@ -130,7 +144,7 @@ impl Gfx {
             let layerNames = [CString::new("VK_LAYER_KHRONOS_validation").unwrap()];
             let layerNames: Vec<_> = layerNames.iter().map(|s| s.as_ptr()).collect();
 
-            let extensionNames = Self::getExtensions(window);
+            let extensionNames = Self::getExtensions(window, properties2);
 
             let createInfo = vk::InstanceCreateInfo::builder()
                 .application_info(&appInfo)
@@ -186,7 +200,9 @@ impl Gfx {
                 });
             });
 
-        let instance = Self::createInstance(&entry, window)?;
+        let properties2 = Self::supportsProperties2(&entry)?;
+
+        let instance = Self::createInstance(&entry, window, properties2)?;
         #[cfg(debug_assertions)]
         let debugCallback =
             Self::createDebugMessenger(&entry, &instance, Some(vulkanDebugCallback))?;
@@ -195,7 +211,7 @@ impl Gfx {
 
         let surfaceLoader = Surface::new(&entry, &instance);
 
-        let pdevice = PhysicalDevice::pickOptimal(&entry, &instance, surface, &surfaceLoader)?;
+        let pdevice = PhysicalDevice::pickOptimal(&entry, &instance, surface, &surfaceLoader, properties2)?;
 
         log::debug!("Created physical device");
         log::info!("{}", pdevice.getGPUProperties().name());
@@ -215,6 +231,8 @@ impl Gfx {
 
         log::debug!("Created swapchain");
 
+        // The test shader is GLSL, which needs the runtime compiler.
+        #[cfg(feature = "shaderc")]
         let vert = r"#version 450 core
         #extension GL_ARB_separate_shader_objects : enable
         
@@ -230,6 +248,7 @@ impl Gfx {
             fragColor = positions[gl_VertexIndex].xyx;
         }";
 
+        #[cfg(feature = "shaderc")]
         let frag = r"#version 450 core
         #extension GL_ARB_separate_shader_objects : enable
         
@@ -242,19 +261,15 @@ impl Gfx {
             color = vec4(vec3(sin(fragColor.x), sin(fragColor.y), sin(fragColor.z)), 1.0);
         }";
 
+        #[cfg(feature = "shaderc")]
         use super::shader::*;
 
+        #[cfg(feature = "shaderc")]
         let shader = Shader::create(
             &device,
             "test".into(),
-            ShaderCompileInfo {
-                entryPoint: "main".into(),
-                data: ShaderData::Source(vert.into()),
-            },
-            ShaderCompileInfo {
-                entryPoint: "main".into(),
-                data: ShaderData::Source(frag.into()),
-            },
+            ShaderCompileInfo::new("main", ShaderData::Source(vert.into())),
+            ShaderCompileInfo::new("main", ShaderData::Source(frag.into())),
         )?;
 
         Ok(Self {
@@ -3,6 +3,7 @@ extern crate log;
 pub mod buffer;
 pub mod command;
 pub mod device;
+pub mod dynamicrendering;
 pub mod framebuffer;
 pub mod graph;
 pub mod graphics;
@@ -16,6 +17,7 @@ pub use device::Device;
 pub use device::PhysicalDevice;
 pub use graphics::Gfx;
 pub use image::ImageView;
+pub use shader::ComputeShader;
 pub use shader::Shader;
 pub use texture::ColorFormat;
 pub use texture::DepthStencilFormat;
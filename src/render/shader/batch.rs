@@ -0,0 +1,92 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use super::{boxed, CompileError, CompiledStages, Shader, ShaderCompileInfo, ShaderStage, ShaderStages};
+
+/// Runs `f` on every item, spread over one thread per core; results come back in the order of `items`.
+fn parallelMap<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(items.len());
+
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+
+                let result = f(item);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed before the scope ends"))
+        .collect()
+}
+
+/// SPIR-V of one stage along with every file it was built from.
+type CompileResult = Result<(Vec<u8>, Vec<PathBuf>), CompileError>;
+
+impl ShaderCompileInfo {
+    /// Compiles every `(name, compileInfo, stage)` in parallel, each worker thread with its own compiler.
+    ///
+    /// Results are in the same order as `stages`; one failing doesn't stop the others.
+    pub fn compileAll(
+        stages: &[(String, ShaderCompileInfo, ShaderStage)],
+    ) -> Vec<CompileResult> {
+        parallelMap(stages, |(name, compileInfo, stage)| compileInfo.compile(name, *stage))
+    }
+}
+
+impl Shader {
+    /// Creates many shaders at once, e.g. at startup. Compilation runs in parallel, module creation and
+    /// registration afterwards on the calling thread.
+    ///
+    /// Results are in the same order as `shaders`.
+    pub fn createAll(
+        device: &Arc<crate::Device>,
+        shaders: Vec<(String, ShaderStages)>,
+    ) -> Vec<Result<Arc<Self>, Box<dyn std::error::Error>>> {
+        let features = device.enabledFeatures();
+        let compiled =
+            parallelMap(&shaders, |(name, stages)| CompiledStages::compile(name, stages.clone(), Some(features)));
+
+        shaders
+            .into_iter()
+            .zip(compiled)
+            .map(|((name, _), compiled)| {
+                compiled
+                    .map_err(boxed)
+                    .and_then(|compiled| Self::fromCompiled(device, name, compiled))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,107 @@
+use super::reflection::BlockLayout;
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum ShaderBlockError {
+    #[error("No uniform or storage block named {0:?} in shader {1:?}!")]
+    UnknownBlock(String, String),
+    #[error("{0}: no field corresponding to block member {1:?}!")]
+    MissingField(&'static str, String),
+    #[error("{0}: field {1:?} is at offset {2} with size {3}, but the shader expects offset {4} with size {5}!")]
+    FieldMismatch(&'static str, String, u32, u32, u32, u32),
+    #[error("{0}: struct is {1} bytes, but block {2:?} is {3} bytes!")]
+    StructTooSmall(&'static str, u32, String, u32),
+}
+
+#[derive(Clone)]
+pub struct BlockField {
+    pub name: &'static str,
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// A `#[repr(C)]` struct matching a uniform or storage block, as generated by `codegen::generateBlock`.
+///
+/// Implement with the `shaderBlock!` macro, which derives the field offsets from the struct itself;
+/// field names have to match the member names of the block. Nothing checks a struct on its own: call
+/// `Shader::validateBlock` (or `BlockLayout::validate`) before uploading it, e.g. once when setting up a material.
+///
+/// # Safety
+///
+/// The struct's bytes are copied into the buffer as they are, so it must be `#[repr(C)]`, contain no pointers or
+/// references, and `fields` must report each field's true offset and size within it.
+pub unsafe trait ShaderBlock: Copy + 'static {
+    fn fields() -> Vec<BlockField>;
+}
+
+#[macro_export]
+macro_rules! shaderBlock {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        unsafe impl $crate::render::shader::ShaderBlock for $ty {
+            fn fields() -> Vec<$crate::render::shader::BlockField> {
+                fn sizeOfPointee<T>(_: *const T) -> usize {
+                    std::mem::size_of::<T>()
+                }
+
+                let uninit = std::mem::MaybeUninit::<$ty>::uninit();
+                let base = uninit.as_ptr();
+
+                vec![$({
+                    let field = unsafe { std::ptr::addr_of!((*base).$field) };
+
+                    $crate::render::shader::BlockField {
+                        name: stringify!($field),
+                        offset: (field as usize - base as usize) as u32,
+                        size: sizeOfPointee(field) as u32,
+                    }
+                }),*]
+            }
+        }
+    };
+}
+
+impl BlockLayout {
+    /// Checks that `T` lays out every member where the shader expects it.
+    ///
+    /// A trailing runtime-sized array has no counterpart in a sized struct, so it's left out; members of nested
+    /// structs are only checked as a whole.
+    pub fn validate<T: ShaderBlock>(&self) -> Result<(), ShaderBlockError> {
+        let typeName = std::any::type_name::<T>();
+        let fields = T::fields();
+
+        for member in self.members() {
+            if member.arrayDimensions().contains(&0) {
+                continue;
+            }
+
+            let field = fields
+                .iter()
+                .find(|field| field.name.trim_start_matches("r#") == member.name())
+                .ok_or_else(|| ShaderBlockError::MissingField(typeName, member.name().to_owned()))?;
+
+            if field.offset != member.offset() || field.size != member.size() {
+                return Err(ShaderBlockError::FieldMismatch(
+                    typeName,
+                    member.name().to_owned(),
+                    field.offset,
+                    field.size,
+                    member.offset(),
+                    member.size(),
+                ));
+            }
+        }
+
+        let size = std::mem::size_of::<T>() as u32;
+
+        if size < self.size() {
+            return Err(ShaderBlockError::StructTooSmall(
+                typeName,
+                size,
+                self.typeName().to_owned(),
+                self.size(),
+            ));
+        }
+
+        Ok(())
+    }
+}
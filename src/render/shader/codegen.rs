@@ -0,0 +1,235 @@
+//! Generates `#[repr(C)]` Rust structs for uniform and storage blocks from their reflected layout.
+//!
+//! Fields sit exactly at the reflected offsets, with explicit `_padN` byte arrays in between, so the structs
+//! can be uploaded as they are. Each struct also gets a `shaderBlock!` implementation, which lets
+//! `BlockLayout::validate` check it against the shader at runtime.
+
+use std::collections::BTreeMap;
+
+use super::{
+    reflection::{BlockLayout, BlockMember, ReflectionData},
+    ScalarType, ShaderDataType,
+};
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum CodegenError {
+    #[error("Two blocks named {0:?} have different layouts (e.g. std140 and std430), one of them has to be renamed!")]
+    LayoutMismatch(String),
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+fn fieldName(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// `camera_data` and `cameraData` both become `CameraData`.
+fn typeName(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut characters = part.chars();
+
+            match characters.next() {
+                Some(first) => first.to_uppercase().chain(characters).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn scalarType(scalar: ScalarType) -> &'static str {
+    match scalar {
+        ScalarType::Bool | ScalarType::UInt32 => "u32",
+        ScalarType::Int8 => "i8",
+        ScalarType::UInt8 => "u8",
+        ScalarType::Int16 => "i16",
+        // Rust has no half float type, the raw bits have to do.
+        ScalarType::UInt16 | ScalarType::Float16 => "u16",
+        ScalarType::Int32 => "i32",
+        ScalarType::Int64 => "i64",
+        ScalarType::UInt64 => "u64",
+        ScalarType::Float32 => "f32",
+        ScalarType::Float64 => "f64",
+    }
+}
+
+/// Collects the structs of one block, nested structs and padded array elements included.
+struct Generator {
+    definitions: Vec<String>,
+}
+impl Generator {
+    /// Emits a struct for `members`, whose offsets are relative to `baseOffset`, padded to `size` bytes.
+    fn structure(&mut self, name: &str, members: &[BlockMember], baseOffset: u32, size: u32) {
+        let mut members: Vec<_> = members.iter().collect();
+        members.sort_by_key(|member| member.offset());
+
+        let mut lines = Vec::new();
+        let mut fields = Vec::new();
+        let mut cursor = 0;
+        let mut padding = 0;
+
+        let mut pad = |lines: &mut Vec<String>, bytes: u32| {
+            lines.push(format!("    pub _pad{}: [u8; {}],", padding, bytes));
+            padding += 1;
+        };
+
+        for member in members {
+            let offset = member.offset() - baseOffset;
+
+            if member.arrayDimensions().contains(&0) {
+                lines.push(format!(
+                    "    // `{}`: runtime-sized array starting at offset {} with a stride of {} bytes",
+                    member.name(),
+                    offset,
+                    member.arrayStride()
+                ));
+                continue;
+            }
+
+            if offset > cursor {
+                pad(&mut lines, offset - cursor);
+            }
+
+            let field = fieldName(member.name());
+            lines.push(format!("    pub {}: {},", field, self.memberType(name, member)));
+            fields.push(field);
+
+            cursor = offset + member.size();
+        }
+
+        if size > cursor {
+            pad(&mut lines, size - cursor);
+        }
+
+        self.definitions.push(format!(
+            "#[repr(C)]\n#[derive(Clone, Copy, Debug)]\npub struct {} {{\n{}\n}}\n::VkRender::shaderBlock!({} {{ {} }});\n",
+            name,
+            lines.join("\n"),
+            name,
+            fields.join(", ")
+        ));
+    }
+    fn memberType(&mut self, parent: &str, member: &BlockMember) -> String {
+        let dimensions = member.arrayDimensions();
+        let elementSize = member.size() / dimensions.iter().product::<u32>().max(1);
+
+        let baseType = |dataType: &ShaderDataType| {
+            let mut dataType = dataType;
+
+            while let ShaderDataType::Array(element, _) = dataType {
+                dataType = element;
+            }
+
+            dataType.clone()
+        };
+
+        let (element, size) = if !member.members().is_empty() {
+            let name = format!("{}{}", parent, typeName(member.name()));
+            self.structure(&name, member.members(), member.offset(), elementSize);
+
+            (name, elementSize)
+        } else {
+            match member.dataType().map(baseType) {
+                Some(ShaderDataType::Scalar(scalar)) => (scalarType(scalar).to_owned(), scalar.size()),
+                Some(ShaderDataType::Vector(scalar, count)) => {
+                    (format!("[{}; {}]", scalarType(scalar), count), scalar.size() * count)
+                }
+                // Columns are padded to the matrix stride, which the reflected size tells us.
+                Some(ShaderDataType::Matrix(scalar, columns, _)) => (
+                    format!(
+                        "[[{}; {}]; {}]",
+                        scalarType(scalar),
+                        elementSize / columns / scalar.size(),
+                        columns
+                    ),
+                    elementSize,
+                ),
+                _ => (format!("[u8; {}]", elementSize), elementSize),
+            }
+        };
+
+        if dimensions.is_empty() {
+            return element;
+        }
+
+        // std140 rounds array strides up to 16 bytes, so the elements need their own padding.
+        let element = if elementSize > size {
+            let name = format!("{}{}Element", parent, typeName(member.name()));
+
+            self.definitions.push(format!(
+                "#[repr(C)]\n#[derive(Clone, Copy, Debug)]\npub struct {} {{\n    pub value: {},\n    pub _pad: [u8; {}],\n}}\n",
+                name,
+                element,
+                elementSize - size
+            ));
+
+            name
+        } else {
+            element
+        };
+
+        dimensions
+            .iter()
+            .rev()
+            .fold(element, |element, length| format!("[{}; {}]", element, length))
+    }
+}
+
+/// Rust source for the struct matching `block`, followed by any structs it depends on.
+///
+/// `name` is the struct's name; nested structs and padded array elements are named after it.
+pub fn generateBlock(name: &str, block: &BlockLayout) -> String {
+    let mut generator = Generator {
+        definitions: Vec::new(),
+    };
+
+    generator.structure(name, block.members(), 0, block.size());
+
+    // Nested structs are pushed before the struct using them, reversed the block itself comes first.
+    generator.definitions.reverse();
+    generator.definitions.join("\n")
+}
+
+/// Structs for every uniform and storage block of a shader, named after the block types.
+///
+/// `generated` holds the blocks emitted so far by name, so that blocks shared between shaders are only emitted
+/// once. A block with the same name but a different layout can't share the struct and is an error.
+pub fn generateBlocks(
+    reflectionData: &ReflectionData,
+    generated: &mut BTreeMap<String, BlockLayout>,
+) -> Result<String, CodegenError> {
+    let mut structs = Vec::new();
+
+    for binding in reflectionData.bindings() {
+        let block = match binding.block() {
+            Some(block) => block,
+            None => continue,
+        };
+
+        let name = match block.typeName() {
+            "" => typeName(binding.name()),
+            name => typeName(name),
+        };
+
+        match generated.get(&name) {
+            Some(existing) if existing == block => continue,
+            Some(_) => return Err(CodegenError::LayoutMismatch(name)),
+            None => {}
+        }
+
+        structs.push(generateBlock(&name, block));
+        generated.insert(name, block.clone());
+    }
+
+    Ok(structs.join("\n"))
+}
@@ -0,0 +1,367 @@
+use std::convert::TryFrom;
+
+use spirv_reflect::types::{ReflectFormat, ReflectTypeDescription, ReflectTypeFlags};
+
+use thiserror::Error;
+#[derive(Error, Debug)]
+pub enum ShaderDataTypeError {
+    #[error("Unsupported vertex/interface format {0:?}!")]
+    UnsupportedFormat(ReflectFormat),
+    #[error("Unsupported type {0:?} (flags {1:?})!")]
+    UnsupportedType(String, ReflectTypeFlags),
+    #[error("Unsupported {0}-bit {1} scalar!")]
+    UnsupportedScalar(u32, &'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+    Bool,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float16,
+    Float32,
+    Float64,
+}
+impl ScalarType {
+    /// Size in bytes; booleans take 4 bytes in buffers.
+    pub fn size(&self) -> u32 {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 | Self::Float16 => 2,
+            Self::Bool | Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Int64 | Self::UInt64 | Self::Float64 => 8,
+        }
+    }
+    fn reflect(flags: ReflectTypeFlags, width: u32, signedness: u32) -> Result<Self, ShaderDataTypeError> {
+        if flags.contains(ReflectTypeFlags::BOOL) {
+            return Ok(Self::Bool);
+        }
+
+        if flags.contains(ReflectTypeFlags::FLOAT) {
+            return match width {
+                16 => Ok(Self::Float16),
+                32 => Ok(Self::Float32),
+                64 => Ok(Self::Float64),
+                _ => Err(ShaderDataTypeError::UnsupportedScalar(width, "float")),
+            };
+        }
+
+        match (width, signedness != 0) {
+            (8, true) => Ok(Self::Int8),
+            (8, false) => Ok(Self::UInt8),
+            (16, true) => Ok(Self::Int16),
+            (16, false) => Ok(Self::UInt16),
+            (32, true) => Ok(Self::Int32),
+            (32, false) => Ok(Self::UInt32),
+            (64, true) => Ok(Self::Int64),
+            (64, false) => Ok(Self::UInt64),
+            _ => Err(ShaderDataTypeError::UnsupportedScalar(width, "integer")),
+        }
+    }
+}
+
+/// Rules for laying out buffer contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryLayout {
+    /// Uniform buffers: arrays and structs are aligned to 16 bytes.
+    Std140,
+    /// Storage buffers and push constants.
+    Std430,
+    /// VK_EXT_scalar_block_layout: everything is aligned to its scalar type.
+    Scalar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderDataType {
+    Scalar(ScalarType),
+    /// Scalar type and component count (2 to 4).
+    Vector(ScalarType, u32),
+    /// Scalar type, column count and row count; matrices are column major.
+    Matrix(ScalarType, u32, u32),
+    /// Element type and length, `None` for runtime-sized arrays.
+    Array(Box<ShaderDataType>, Option<u32>),
+    /// Member types in declaration order.
+    Struct(Vec<ShaderDataType>),
+}
+impl ShaderDataType {
+    pub const INT: Self = Self::Scalar(ScalarType::Int32);
+    pub const UINT: Self = Self::Scalar(ScalarType::UInt32);
+    pub const FLOAT: Self = Self::Scalar(ScalarType::Float32);
+    pub const VEC2: Self = Self::Vector(ScalarType::Float32, 2);
+    pub const VEC3: Self = Self::Vector(ScalarType::Float32, 3);
+    pub const VEC4: Self = Self::Vector(ScalarType::Float32, 4);
+    pub const MAT4: Self = Self::Matrix(ScalarType::Float32, 4, 4);
+
+    /// Scalar type and component count of scalars and vectors.
+    pub fn components(&self) -> Option<(ScalarType, u32)> {
+        match *self {
+            Self::Scalar(scalar) => Some((scalar, 1)),
+            Self::Vector(scalar, count) => Some((scalar, count)),
+            _ => None,
+        }
+    }
+    /// Whether an output of this type can be read by the next stage as `input`.
+    ///
+    /// Vulkan lets an output have more components than the input it feeds, as long as the component type matches.
+    pub fn canFeed(&self, input: &ShaderDataType) -> bool {
+        match (self.components(), input.components()) {
+            (Some((outputType, outputCount)), Some((inputType, inputCount))) => {
+                outputType == inputType && outputCount >= inputCount
+            }
+            _ => self == input,
+        }
+    }
+    pub fn alignment(&self, layout: MemoryLayout) -> u32 {
+        match self {
+            Self::Scalar(scalar) => scalar.size(),
+            Self::Vector(scalar, count) => match layout {
+                MemoryLayout::Scalar => scalar.size(),
+                _ if *count == 2 => scalar.size() * 2,
+                _ => scalar.size() * 4,
+            },
+            Self::Matrix(scalar, _, rows) => {
+                Self::Array(Box::new(Self::Vector(*scalar, *rows)), None).alignment(layout)
+            }
+            Self::Array(element, _) => match layout {
+                MemoryLayout::Std140 => roundUp(element.alignment(layout), 16),
+                _ => element.alignment(layout),
+            },
+            Self::Struct(members) => {
+                let alignment = members
+                    .iter()
+                    .map(|member| member.alignment(layout))
+                    .max()
+                    .unwrap_or(1);
+
+                match layout {
+                    MemoryLayout::Std140 => roundUp(alignment, 16),
+                    _ => alignment,
+                }
+            }
+        }
+    }
+    /// Size in bytes; runtime-sized arrays count as empty.
+    pub fn size(&self, layout: MemoryLayout) -> u32 {
+        match self {
+            Self::Scalar(scalar) => scalar.size(),
+            Self::Vector(scalar, count) => scalar.size() * count,
+            Self::Matrix(scalar, columns, rows) => {
+                Self::Array(Box::new(Self::Vector(*scalar, *rows)), Some(*columns)).size(layout)
+            }
+            Self::Array(element, length) => element.arrayStride(layout) * length.unwrap_or(0),
+            Self::Struct(members) => {
+                let end = members.iter().fold(0, |offset, member| {
+                    roundUp(offset, member.alignment(layout)) + member.size(layout)
+                });
+
+                match layout {
+                    MemoryLayout::Scalar => end,
+                    _ => roundUp(end, self.alignment(layout)),
+                }
+            }
+        }
+    }
+    /// Distance between consecutive elements of an array of this type.
+    pub fn arrayStride(&self, layout: MemoryLayout) -> u32 {
+        let alignment = match layout {
+            MemoryLayout::Std140 => roundUp(self.alignment(layout), 16),
+            _ => self.alignment(layout),
+        };
+
+        roundUp(self.size(layout), alignment)
+    }
+    /// Offsets of each member of a struct, `None` for other types.
+    pub fn memberOffsets(&self, layout: MemoryLayout) -> Option<Vec<u32>> {
+        let members = match self {
+            Self::Struct(members) => members,
+            _ => return None,
+        };
+
+        let mut offset = 0;
+
+        Some(
+            members
+                .iter()
+                .map(|member| {
+                    let memberOffset = roundUp(offset, member.alignment(layout));
+                    offset = memberOffset + member.size(layout);
+
+                    memberOffset
+                })
+                .collect(),
+        )
+    }
+}
+
+fn roundUp(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+impl TryFrom<ReflectFormat> for ShaderDataType {
+    type Error = ShaderDataTypeError;
+
+    fn try_from(format: ReflectFormat) -> Result<Self, Self::Error> {
+        use ScalarType::*;
+
+        let (scalar, count) = match format {
+            ReflectFormat::R32_UINT => (UInt32, 1),
+            ReflectFormat::R32_SINT => (Int32, 1),
+            ReflectFormat::R32_SFLOAT => (Float32, 1),
+            ReflectFormat::R32G32_UINT => (UInt32, 2),
+            ReflectFormat::R32G32_SINT => (Int32, 2),
+            ReflectFormat::R32G32_SFLOAT => (Float32, 2),
+            ReflectFormat::R32G32B32_UINT => (UInt32, 3),
+            ReflectFormat::R32G32B32_SINT => (Int32, 3),
+            ReflectFormat::R32G32B32_SFLOAT => (Float32, 3),
+            ReflectFormat::R32G32B32A32_UINT => (UInt32, 4),
+            ReflectFormat::R32G32B32A32_SINT => (Int32, 4),
+            ReflectFormat::R32G32B32A32_SFLOAT => (Float32, 4),
+            ReflectFormat::Undefined => return Err(ShaderDataTypeError::UnsupportedFormat(format)),
+        };
+
+        Ok(match count {
+            1 => Self::Scalar(scalar),
+            _ => Self::Vector(scalar, count),
+        })
+    }
+}
+
+impl TryFrom<&ReflectTypeDescription> for ShaderDataType {
+    type Error = ShaderDataTypeError;
+
+    fn try_from(description: &ReflectTypeDescription) -> Result<Self, Self::Error> {
+        let flags = description.type_flags;
+        let numeric = &description.traits.numeric;
+
+        let unsupported = || ShaderDataTypeError::UnsupportedType(description.type_name.to_owned(), flags);
+
+        let base = if flags.contains(ReflectTypeFlags::STRUCT) {
+            Self::Struct(
+                description
+                    .members
+                    .iter()
+                    .map(Self::try_from)
+                    .collect::<Result<_, _>>()?,
+            )
+        } else if flags.intersects(ReflectTypeFlags::EXTERNAL_MASK)
+            || !flags.intersects(ReflectTypeFlags::BOOL | ReflectTypeFlags::INT | ReflectTypeFlags::FLOAT)
+        {
+            return Err(unsupported());
+        } else {
+            let scalar = ScalarType::reflect(flags, numeric.scalar.width, numeric.scalar.signedness)?;
+
+            if flags.contains(ReflectTypeFlags::MATRIX) {
+                Self::Matrix(scalar, numeric.matrix.column_count, numeric.matrix.row_count)
+            } else if flags.contains(ReflectTypeFlags::VECTOR) {
+                Self::Vector(scalar, numeric.vector.component_count)
+            } else {
+                Self::Scalar(scalar)
+            }
+        };
+
+        if !flags.contains(ReflectTypeFlags::ARRAY) {
+            return Ok(base);
+        }
+
+        // `float x[2][3]` has dims [2, 3]: the last dimension is the innermost array. A length of 0 marks a
+        // runtime-sized array.
+        Ok(description
+            .traits
+            .array
+            .dims
+            .iter()
+            .rev()
+            .fold(base, |element, &length| {
+                Self::Array(Box::new(element), if length == 0 { None } else { Some(length) })
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MemoryLayout::*;
+
+    fn array(element: ShaderDataType, length: u32) -> ShaderDataType {
+        ShaderDataType::Array(Box::new(element), Some(length))
+    }
+
+    #[test]
+    fn scalarsAndVectors() {
+        let float = ShaderDataType::FLOAT;
+        let vec3 = ShaderDataType::VEC3;
+
+        for layout in [Std140, Std430] {
+            assert_eq!((float.size(layout), float.alignment(layout)), (4, 4));
+            assert_eq!((ShaderDataType::VEC2.size(layout), ShaderDataType::VEC2.alignment(layout)), (8, 8));
+            assert_eq!((vec3.size(layout), vec3.alignment(layout)), (12, 16));
+            assert_eq!(vec3.arrayStride(layout), 16);
+        }
+
+        assert_eq!(vec3.alignment(Scalar), 4);
+        assert_eq!(vec3.arrayStride(Scalar), 12);
+    }
+
+    #[test]
+    fn arrays() {
+        let floats = array(ShaderDataType::FLOAT, 4);
+
+        assert_eq!(ShaderDataType::FLOAT.arrayStride(Std140), 16);
+        assert_eq!((floats.size(Std140), floats.alignment(Std140)), (64, 16));
+
+        assert_eq!(ShaderDataType::FLOAT.arrayStride(Std430), 4);
+        assert_eq!((floats.size(Std430), floats.alignment(Std430)), (16, 4));
+
+        assert_eq!(ShaderDataType::Array(Box::new(ShaderDataType::VEC4), None).size(Std430), 0);
+    }
+
+    #[test]
+    fn matrices() {
+        let mat2 = ShaderDataType::Matrix(ScalarType::Float32, 2, 2);
+        let mat3 = ShaderDataType::Matrix(ScalarType::Float32, 3, 3);
+
+        assert_eq!((mat2.size(Std140), mat2.alignment(Std140)), (32, 16));
+        assert_eq!((mat2.size(Std430), mat2.alignment(Std430)), (16, 8));
+
+        for layout in [Std140, Std430] {
+            assert_eq!((mat3.size(layout), mat3.alignment(layout)), (48, 16));
+            assert_eq!((ShaderDataType::MAT4.size(layout), ShaderDataType::MAT4.alignment(layout)), (64, 16));
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let block = ShaderDataType::Struct(vec![
+            ShaderDataType::FLOAT,
+            ShaderDataType::VEC2,
+            array(ShaderDataType::FLOAT, 2),
+            ShaderDataType::MAT4,
+        ]);
+
+        assert_eq!(block.memberOffsets(Std140).unwrap(), [0, 8, 16, 48]);
+        assert_eq!((block.size(Std140), block.alignment(Std140)), (112, 16));
+
+        assert_eq!(block.memberOffsets(Std430).unwrap(), [0, 8, 16, 32]);
+        assert_eq!((block.size(Std430), block.alignment(Std430)), (96, 16));
+
+        // A vec3 followed by a float shares its 16 bytes.
+        let packed = ShaderDataType::Struct(vec![ShaderDataType::VEC3, ShaderDataType::FLOAT]);
+        assert_eq!(packed.memberOffsets(Std140).unwrap(), [0, 12]);
+        assert_eq!(packed.size(Std140), 16);
+
+        // Structs are rounded up to 16 bytes in std140 only.
+        let single = ShaderDataType::Struct(vec![ShaderDataType::FLOAT]);
+        assert_eq!((single.size(Std140), single.arrayStride(Std140)), (16, 16));
+        assert_eq!((single.size(Std430), single.arrayStride(Std430)), (4, 4));
+
+        let scalar = ShaderDataType::Struct(vec![ShaderDataType::FLOAT, ShaderDataType::VEC3]);
+        assert_eq!(scalar.memberOffsets(Scalar).unwrap(), [0, 4]);
+        assert_eq!(scalar.size(Scalar), 16);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{fmt, path::PathBuf, sync::RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+impl Severity {
+    fn marker(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+        }
+    }
+}
+
+/// A single compiler message.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// File name as the compiler reported it; the shader name for sources that didn't come from a file.
+    pub file: String,
+    /// The file on disk `file` refers to, for the main source file and resolved includes.
+    pub path: Option<PathBuf>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+
+        write!(f, ": {}: {}", self.severity.marker(), self.message)
+    }
+}
+
+pub(crate) fn formatDiagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits `file:line:column` (line and column being optional) from the right, so that drive letters and
+/// other colons in the file name survive.
+#[cfg(feature = "shaderc")]
+fn parseLocation(location: &str) -> (String, Option<u32>, Option<u32>) {
+    let mut parts: Vec<&str> = location.rsplitn(3, ':').collect();
+    parts.reverse();
+
+    match parts.as_slice() {
+        [file, middle, last] => match (middle.trim().parse(), last.trim().parse()) {
+            (Ok(line), Ok(column)) => return ((*file).to_owned(), Some(line), Some(column)),
+            // `C:\dir\x.glsl:12`: the middle part belongs to the file name.
+            (_, Ok(line)) => return (format!("{}:{}", file, middle), Some(line), None),
+            _ => {}
+        },
+        [file, line] => {
+            if let Ok(line) = line.trim().parse() {
+                return ((*file).to_owned(), Some(line), None);
+            }
+        }
+        _ => {}
+    }
+
+    (location.to_owned(), None, None)
+}
+
+/// Parses shaderc's (glslang's) `file:line: severity: message` output.
+///
+/// `resolvePath` maps a reported file name to the file on disk, if there is one. Lines that aren't
+/// diagnostics themselves are appended to the previous diagnostic, summaries like "1 error generated." are dropped.
+#[cfg(feature = "shaderc")]
+pub(crate) fn parse(output: &str, resolvePath: impl Fn(&str) -> Option<PathBuf>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for line in output.lines().map(str::trim_end).filter(|line| !line.is_empty()) {
+        let parsed = [Severity::Error, Severity::Warning, Severity::Note]
+            .iter()
+            .filter_map(|&severity| {
+                let marker = format!(": {}: ", severity.marker());
+
+                line.find(&marker)
+                    .map(|index| (index, severity, &line[index + marker.len()..]))
+            })
+            .min_by_key(|&(index, _, _)| index);
+
+        match parsed {
+            Some((index, severity, message)) => {
+                let (file, line, column) = parseLocation(&line[..index]);
+
+                diagnostics.push(Diagnostic {
+                    path: resolvePath(&file),
+                    file,
+                    line,
+                    column,
+                    severity,
+                    message: message.to_owned(),
+                });
+            }
+            None if line.ends_with("generated.") => {}
+            None => match diagnostics.last_mut() {
+                Some(last) => {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+                None => diagnostics.push(Diagnostic {
+                    file: String::new(),
+                    path: None,
+                    line: None,
+                    column: None,
+                    severity: Severity::Error,
+                    message: line.to_owned(),
+                }),
+            },
+        }
+    }
+
+    diagnostics
+}
+
+/// Receives the errors and warnings of every shader compilation, e.g. to show them in an editor.
+pub trait DiagnosticSink: Send + Sync {
+    fn report(&self, shaderName: &str, diagnostics: &[Diagnostic]);
+}
+impl<F: Fn(&str, &[Diagnostic]) + Send + Sync> DiagnosticSink for F {
+    fn report(&self, shaderName: &str, diagnostics: &[Diagnostic]) {
+        self(shaderName, diagnostics)
+    }
+}
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Box<dyn DiagnosticSink>>> = RwLock::new(None);
+}
+
+/// Sends diagnostics of all later compilations to `sink`, in addition to the log.
+///
+/// Shaders served from the disk cache aren't compiled again, so the warnings they produced back then are
+/// dropped; clear the cache to see them again.
+pub fn setDiagnosticSink(sink: Option<Box<dyn DiagnosticSink>>) {
+    *SINK.write().unwrap() = sink;
+}
+
+#[cfg(any(feature = "shaderc", feature = "wgsl"))]
+pub(crate) fn report(shaderName: &str, diagnostics: &[Diagnostic]) {
+    if diagnostics.is_empty() {
+        return;
+    }
+
+    if let Some(sink) = &*SINK.read().unwrap() {
+        sink.report(shaderName, diagnostics);
+    }
+}
+
+#[cfg(all(test, feature = "shaderc"))]
+mod tests {
+    use super::*;
+
+    fn parseAll(output: &str) -> Vec<Diagnostic> {
+        parse(output, |file| Some(PathBuf::from(file)).filter(|_| file == "shader.vert"))
+    }
+
+    #[test]
+    fn lineWithoutColumn() {
+        let diagnostics = parseAll("shader.vert:12: error: 'x' : undeclared identifier\n1 error generated.\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "shader.vert");
+        assert_eq!(diagnostics[0].path, Some(PathBuf::from("shader.vert")));
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(12), None));
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "'x' : undeclared identifier");
+    }
+
+    #[test]
+    fn lineAndColumn() {
+        let diagnostics = parseAll("common.glsl:3:5: warning: unused variable");
+
+        assert_eq!(diagnostics[0].file, "common.glsl");
+        assert_eq!(diagnostics[0].path, None);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(3), Some(5)));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn driveLetters() {
+        let diagnostics = parseAll(
+            "C:\\shaders\\x.frag:7: error: first\nC:\\shaders\\x.frag:8:2: note: second\nC:\\shaders\\x.frag: error: third",
+        );
+
+        let locations: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.file.as_str(), diagnostic.line, diagnostic.column))
+            .collect();
+
+        assert_eq!(
+            locations,
+            [
+                ("C:\\shaders\\x.frag", Some(7), None),
+                ("C:\\shaders\\x.frag", Some(8), Some(2)),
+                ("C:\\shaders\\x.frag", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn continuationLines() {
+        let diagnostics = parseAll("shader.vert:4: error: syntax error\n  near token 'vec3'\nunrelated.glsl: note: x");
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "syntax error\n  near token 'vec3'");
+        assert_eq!((diagnostics[1].file.as_str(), diagnostics[1].line), ("unrelated.glsl", None));
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    convert::TryInto,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::SystemTime,
+};
+
+use ash::vk;
+
+use super::{CompileError, ShaderCompileInfo, ShaderData, ShaderStage};
+
+const ENTRY_MAGIC: &[u8; 4] = b"VKSC";
+/// Bump whenever the entry layout or the key material changes.
+const ENTRY_VERSION: u32 = 4;
+const ENTRY_EXTENSION: &str = "spvcache";
+
+static TEMPORARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across runs and compiler versions.
+pub(crate) struct FnvHasher(u64);
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+pub(crate) fn hashBytes(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+
+    hasher.finish()
+}
+
+/// Everything that affects the compiled SPIR-V, except the included files.
+///
+/// Includes are only known after compiling, so entries list them along with a hash of their contents and
+/// are treated as stale as soon as one of them changes.
+// The device features only matter to WGSL, through the capabilities it's validated with.
+#[cfg_attr(not(feature = "wgsl"), allow(unused_variables))]
+pub(crate) fn cacheKey(
+    compileInfo: &ShaderCompileInfo,
+    stage: ShaderStage,
+    features: Option<&vk::PhysicalDeviceFeatures>,
+) -> u64 {
+    let mut hasher = FnvHasher::default();
+
+    ENTRY_VERSION.hash(&mut hasher);
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+
+    // libshaderc doesn't report its own version; the SPIR-V version and revision it generates are the closest
+    // thing, and change whenever the linked library is updated to newer SPIR-V headers.
+    #[cfg(feature = "shaderc")]
+    shaderc::get_spirv_version().hash(&mut hasher);
+
+    match &compileInfo.data {
+        ShaderData::Source(text) => {
+            "glsl".hash(&mut hasher);
+            text.hash(&mut hasher);
+        }
+        ShaderData::Hlsl(text, options) => {
+            "hlsl".hash(&mut hasher);
+            text.hash(&mut hasher);
+            options.hash(&mut hasher);
+        }
+        ShaderData::Spirv(bytes) => {
+            "spirv".hash(&mut hasher);
+            bytes.hash(&mut hasher);
+        }
+        #[cfg(feature = "wgsl")]
+        ShaderData::Wgsl(text) => {
+            "wgsl".hash(&mut hasher);
+            text.hash(&mut hasher);
+            super::wgsl::capabilities(features).bits().hash(&mut hasher);
+        }
+    }
+
+    stage.hash(&mut hasher);
+    compileInfo.entryPoint.hash(&mut hasher);
+    compileInfo.defines.hash(&mut hasher);
+    compileInfo.sourcePath.hash(&mut hasher);
+    compileInfo.includePaths.hash(&mut hasher);
+    compileInfo.effectiveOptions().hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Reads fields of an entry, failing on truncated data.
+struct EntryReader<'a> {
+    data: &'a [u8],
+}
+impl<'a> EntryReader<'a> {
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        if self.data.len() < count {
+            return None;
+        }
+
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+
+        Some(bytes)
+    }
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+}
+
+enum Lookup {
+    Hit(Vec<u8>, Vec<PathBuf>),
+    Stale,
+    Corrupt,
+}
+
+/// A file a compilation read, with the hash of the contents it read.
+pub(crate) type Dependency = (PathBuf, u64);
+
+/// Stores compiled SPIR-V on disk so that later runs can skip compilation.
+///
+/// Every entry is a file named after its key. When the directory grows beyond `maxSize` bytes, the least
+/// recently used entries are evicted.
+pub struct ShaderDiskCache {
+    directory: PathBuf,
+    maxSize: u64,
+    /// Size of the entries as of the last scan plus everything stored since; the directory is only scanned
+    /// again once this exceeds `maxSize`.
+    size: AtomicU64,
+}
+impl ShaderDiskCache {
+    pub fn new<P: Into<PathBuf>>(directory: P, maxSize: u64) -> io::Result<Self> {
+        let directory = directory.into();
+
+        fs::create_dir_all(&directory)?;
+
+        let cache = Self {
+            directory,
+            maxSize,
+            size: AtomicU64::new(0),
+        };
+
+        cache.evict()?;
+
+        Ok(cache)
+    }
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+    fn entryPath(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.{}", key, ENTRY_EXTENSION))
+    }
+    fn parse(key: u64, data: &[u8]) -> Lookup {
+        let mut reader = EntryReader { data };
+
+        let parsed = (|| {
+            if reader.bytes(4)? != ENTRY_MAGIC || reader.u32()? != ENTRY_VERSION || reader.u64()? != key {
+                return None;
+            }
+
+            let mut dependencies = Vec::new();
+
+            for _ in 0..reader.u32()? {
+                let length = reader.u32()? as usize;
+                let path = PathBuf::from(std::str::from_utf8(reader.bytes(length)?).ok()?);
+                let hash = reader.u64()?;
+
+                dependencies.push((path, hash));
+            }
+
+            let length = reader.u32()? as usize;
+            let spirv = reader.bytes(length)?.to_vec();
+
+            if reader.u64()? != hashBytes(&spirv) || !reader.data.is_empty() {
+                return None;
+            }
+
+            Some((dependencies, spirv))
+        })();
+
+        let (dependencies, spirv) = match parsed {
+            Some(parsed) => parsed,
+            None => return Lookup::Corrupt,
+        };
+
+        for (path, hash) in &dependencies {
+            match fs::read(path) {
+                Ok(contents) if hashBytes(&contents) == *hash => {}
+                _ => return Lookup::Stale,
+            }
+        }
+
+        Lookup::Hit(spirv, dependencies.into_iter().map(|(path, _)| path).collect())
+    }
+    /// Cached SPIR-V and dependencies for `key`, unless the entry is missing, stale or corrupt.
+    pub(crate) fn load(&self, key: u64) -> Option<(Vec<u8>, Vec<PathBuf>)> {
+        let path = self.entryPath(key);
+
+        let data = fs::read(&path).ok()?;
+
+        match Self::parse(key, &data) {
+            Lookup::Hit(spirv, dependencies) => {
+                // The modification time doubles as the last use for eviction.
+                if let Ok(file) = File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+
+                Some((spirv, dependencies))
+            }
+            Lookup::Stale => {
+                log::info!("Shader cache entry {:?} is stale, recompiling", path);
+
+                None
+            }
+            Lookup::Corrupt => {
+                log::warn!("Shader cache entry {:?} is corrupt, removing it", path);
+                let _ = fs::remove_file(&path);
+
+                None
+            }
+        }
+    }
+    pub(crate) fn store(&self, key: u64, spirv: &[u8], dependencies: &[Dependency]) -> io::Result<()> {
+        let mut data = Vec::new();
+
+        data.extend_from_slice(ENTRY_MAGIC);
+        data.extend_from_slice(&ENTRY_VERSION.to_le_bytes());
+        data.extend_from_slice(&key.to_le_bytes());
+        data.extend_from_slice(&(dependencies.len() as u32).to_le_bytes());
+
+        for (path, hash) in dependencies {
+            let name = path.to_string_lossy();
+
+            data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&hash.to_le_bytes());
+        }
+
+        data.extend_from_slice(&(spirv.len() as u32).to_le_bytes());
+        data.extend_from_slice(spirv);
+        data.extend_from_slice(&hashBytes(spirv).to_le_bytes());
+
+        // Write to a temporary file first so that a crash can't leave a half-written entry behind. Threads and
+        // processes compiling the same shader at once each get their own.
+        let path = self.entryPath(key);
+        let temporary = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::write(&temporary, &data)?;
+        fs::rename(&temporary, &path)?;
+
+        let size = self.size.fetch_add(data.len() as u64, Ordering::Relaxed) + data.len() as u64;
+
+        if size > self.maxSize {
+            self.evict()?;
+        }
+
+        Ok(())
+    }
+    /// Removes the least recently used entries until the cache fits in `maxSize`.
+    ///
+    /// Other threads and processes may be evicting at the same time, so entries that disappear in the
+    /// meantime are skipped.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            if path.extension().and_then(|extension| extension.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+            entries.push((used, metadata.len(), path));
+        }
+
+        let mut size: u64 = entries.iter().map(|(_, length, _)| length).sum();
+
+        entries.sort();
+
+        for (_, length, path) in entries {
+            if size <= self.maxSize {
+                break;
+            }
+
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            size -= length;
+        }
+
+        self.size.store(size, Ordering::Relaxed);
+
+        Ok(())
+    }
+    /// Removes every entry.
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+
+            if path.extension().and_then(|extension| extension.to_str()) == Some(ENTRY_EXTENSION) {
+                fs::remove_file(path)?;
+            }
+        }
+
+        self.size.store(0, Ordering::Relaxed);
+
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref DISK_CACHE: RwLock<Option<ShaderDiskCache>> = RwLock::new(None);
+}
+
+/// Makes every shader compilation go through `cache`; `None` turns caching off again.
+pub fn setDiskCache(cache: Option<ShaderDiskCache>) {
+    *DISK_CACHE.write().unwrap() = cache;
+}
+
+/// Compiles through the disk cache if one is set, `compile` returns the SPIR-V and its dependencies with the
+/// hashes of the contents it compiled.
+pub(crate) fn cached<F>(
+    compileInfo: &ShaderCompileInfo,
+    stage: ShaderStage,
+    features: Option<&vk::PhysicalDeviceFeatures>,
+    compile: F,
+) -> Result<(Vec<u8>, Vec<PathBuf>), CompileError>
+where
+    F: FnOnce() -> Result<(Vec<u8>, Vec<Dependency>), CompileError>,
+{
+    let withoutHashes = |dependencies: Vec<Dependency>| dependencies.into_iter().map(|(path, _)| path).collect();
+
+    let cache = DISK_CACHE.read().unwrap();
+
+    let cache = match &*cache {
+        Some(cache) => cache,
+        None => return compile().map(|(spirv, dependencies)| (spirv, withoutHashes(dependencies))),
+    };
+
+    let key = cacheKey(compileInfo, stage, features);
+
+    if let Some(hit) = cache.load(key) {
+        return Ok(hit);
+    }
+
+    let (spirv, dependencies) = compile()?;
+
+    if let Err(err) = cache.store(key, &spirv, &dependencies) {
+        log::warn!("Failed to store compiled shader in {:?}: {}", cache.directory(), err);
+    }
+
+    Ok((spirv, withoutHashes(dependencies)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty cache directory of its own for each test.
+    fn testCache(name: &str, maxSize: u64) -> ShaderDiskCache {
+        let directory = std::env::temp_dir().join(format!("vkrender-diskcache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        ShaderDiskCache::new(directory, maxSize).unwrap()
+    }
+
+    #[test]
+    fn entryFormat() {
+        let cache = testCache("format", u64::MAX);
+        let dependency = cache.directory().join("common.glsl");
+        fs::write(&dependency, "float x;").unwrap();
+
+        let spirv = [1, 2, 3, 4, 5, 6, 7, 8];
+        cache.store(42, &spirv, &[(dependency.clone(), hashBytes(b"float x;"))]).unwrap();
+
+        let data = fs::read(cache.entryPath(42)).unwrap();
+        let mut reader = EntryReader { data: &data };
+        assert_eq!(reader.bytes(4).unwrap(), ENTRY_MAGIC);
+        assert_eq!(reader.u32(), Some(ENTRY_VERSION));
+        assert_eq!(reader.u64(), Some(42));
+        assert_eq!(reader.u32(), Some(1));
+        let length = reader.u32().unwrap() as usize;
+        assert_eq!(reader.bytes(length).unwrap(), dependency.to_string_lossy().as_bytes());
+        assert_eq!(reader.u64(), Some(hashBytes(b"float x;")));
+        assert_eq!(reader.u32(), Some(spirv.len() as u32));
+        assert_eq!(reader.bytes(spirv.len()).unwrap(), spirv);
+        assert_eq!(reader.u64(), Some(hashBytes(&spirv)));
+        assert!(reader.data.is_empty());
+
+        assert_eq!(cache.load(42), Some((spirv.to_vec(), vec![dependency])));
+        // An entry is only ever valid for its own key.
+        assert!(matches!(ShaderDiskCache::parse(43, &data), Lookup::Corrupt));
+
+        fs::write(cache.entryPath(42), &data[..data.len() - 1]).unwrap();
+        assert_eq!(cache.load(42), None);
+        assert!(!cache.entryPath(42).exists());
+
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn changedDependencyMisses() {
+        let cache = testCache("dependency", u64::MAX);
+        let dependency = cache.directory().join("common.glsl");
+        fs::write(&dependency, "float x;").unwrap();
+
+        cache.store(7, &[0; 4], &[(dependency.clone(), hashBytes(b"float x;"))]).unwrap();
+        assert!(cache.load(7).is_some());
+
+        fs::write(&dependency, "float y;").unwrap();
+        assert_eq!(cache.load(7), None);
+
+        // Stale entries are kept, changing the file back makes them valid again.
+        fs::write(&dependency, "float x;").unwrap();
+        assert!(cache.load(7).is_some());
+
+        fs::remove_file(&dependency).unwrap();
+        assert_eq!(cache.load(7), None);
+
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn evictsLeastRecentlyUsed() {
+        let cache = testCache("evict", 100);
+
+        cache.store(1, &[0; 40], &[]).unwrap();
+        let old = File::options().write(true).open(cache.entryPath(1)).unwrap();
+        old.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+
+        cache.store(2, &[0; 40], &[]).unwrap();
+
+        assert!(!cache.entryPath(1).exists());
+        assert!(cache.load(2).is_some());
+
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+}
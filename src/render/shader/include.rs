@@ -0,0 +1,87 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "shaderc")]
+use shaderc::{IncludeCallbackResult, IncludeType, ResolvedInclude};
+
+/// Resolves `#include` directives for one compilation and records every file that was pulled in, along with a
+/// hash of the contents the compiler got.
+///
+/// `#include "file"` is looked up next to the including file first, then in the include paths;
+/// `#include <file>` only in the include paths, in order. Shaders that weren't loaded from a file only have
+/// the include paths.
+#[cfg_attr(not(feature = "shaderc"), allow(dead_code))]
+pub(crate) struct IncludeResolver<'a> {
+    includePaths: &'a [PathBuf],
+    sourcePath: Option<&'a Path>,
+    dependencies: RefCell<BTreeMap<PathBuf, u64>>,
+}
+impl<'a> IncludeResolver<'a> {
+    pub(crate) fn new(includePaths: &'a [PathBuf], sourcePath: Option<&'a Path>) -> Self {
+        Self {
+            includePaths,
+            sourcePath,
+            dependencies: RefCell::new(BTreeMap::new()),
+        }
+    }
+    #[cfg(feature = "shaderc")]
+    pub(crate) fn resolve(
+        &self,
+        requested: &str,
+        includeType: IncludeType,
+        requestingSource: &str,
+    ) -> IncludeCallbackResult {
+        // Sources that didn't come from a file have no directory, their relative includes are standard ones.
+        let relative = match includeType {
+            IncludeType::Relative => self
+                .pathOf(requestingSource)
+                .and_then(|path| path.parent().map(|directory| directory.join(requested))),
+            IncludeType::Standard => None,
+        };
+
+        let path = relative
+            .into_iter()
+            .chain(self.includePaths.iter().map(|directory| directory.join(requested)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                format!(
+                    "Couldn't find {:?} included from {:?} in the include paths {:?}",
+                    requested, requestingSource, self.includePaths
+                )
+            })?;
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read include {:?}: {}", path, err))?;
+
+        let path = path.canonicalize().unwrap_or(path);
+
+        self.dependencies
+            .borrow_mut()
+            .insert(path.clone(), super::diskcache::hashBytes(content.as_bytes()));
+
+        Ok(ResolvedInclude {
+            resolved_name: path.to_string_lossy().into_owned(),
+            content,
+        })
+    }
+    /// The file behind a name the compiler reports in diagnostics: the main source file or a resolved include.
+    #[cfg(feature = "shaderc")]
+    pub(crate) fn pathOf(&self, reportedName: &str) -> Option<PathBuf> {
+        self.sourcePath
+            .filter(|path| path.to_string_lossy() == reportedName)
+            .map(Path::to_owned)
+            .or_else(|| {
+                self.dependencies
+                    .borrow()
+                    .keys()
+                    .find(|path| path.to_string_lossy() == reportedName)
+                    .cloned()
+            })
+    }
+    pub(crate) fn intoDependencies(self) -> BTreeMap<PathBuf, u64> {
+        self.dependencies.into_inner()
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use ash::vk;
+
+use super::{ShaderCreateError, ShaderInfo, ShaderStage};
+
+/// Checks that the stages of a graphics shader fit together, so mismatches surface when the shader is created
+/// rather than as validation errors (or worse) at draw time.
+pub(crate) fn validate(name: &str, stages: &[(vk::ShaderStageFlags, &ShaderInfo)]) -> Result<(), ShaderCreateError> {
+    if let [.., (_, last), (_, fragment)] = stages {
+        validateStageInterface(name, last, fragment)?;
+    }
+
+    validateBindings(name, stages)
+}
+
+/// Every fragment input has to be written by the last stage before rasterization, at the same location.
+fn validateStageInterface(name: &str, producer: &ShaderInfo, fragment: &ShaderInfo) -> Result<(), ShaderCreateError> {
+    let outputs = producer.reflectionData.outputs();
+
+    for input in fragment.reflectionData.inputs().values() {
+        let output = outputs
+            .values()
+            .find(|output| output.location == input.location)
+            .ok_or_else(|| {
+                ShaderCreateError::MissingStageOutput(
+                    name.to_owned(),
+                    input.name.to_owned(),
+                    input.location,
+                    producer.stage,
+                )
+            })?;
+
+        if !output.dataType.canFeed(&input.dataType) {
+            return Err(ShaderCreateError::InterfaceTypeMismatch(
+                name.to_owned(),
+                producer.stage,
+                output.name.to_owned(),
+                output.dataType.clone(),
+                input.name.to_owned(),
+                input.dataType.clone(),
+                input.location,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A set and binding used by several stages has to be the same kind of descriptor in each of them.
+fn validateBindings(name: &str, stages: &[(vk::ShaderStageFlags, &ShaderInfo)]) -> Result<(), ShaderCreateError> {
+    let mut seen = HashMap::new();
+
+    for (_, info) in stages {
+        for binding in info.reflectionData.bindings() {
+            let key = (binding.set(), binding.binding());
+
+            let (descriptorType, stage): &(_, ShaderStage) =
+                seen.entry(key).or_insert((binding.descriptorType(), info.stage));
+
+            if *descriptorType != binding.descriptorType() {
+                return Err(ShaderCreateError::BindingTypeMismatch(
+                    name.to_owned(),
+                    binding.set(),
+                    binding.binding(),
+                    *descriptorType,
+                    *stage,
+                    binding.descriptorType(),
+                    info.stage,
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
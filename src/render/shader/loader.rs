@@ -0,0 +1,203 @@
+use std::{
+    ffi::OsStr,
+    path::Path,
+    sync::Arc,
+};
+
+use super::{
+    ComputeShader, HlslOptions, Shader, ShaderCompileInfo, ShaderCreateError, ShaderData, ShaderStage,
+    ShaderStages,
+};
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+
+/// What a shader file contains, according to its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderFileFormat {
+    Glsl,
+    Hlsl,
+    Spirv,
+    #[cfg(feature = "wgsl")]
+    Wgsl,
+}
+
+/// Detects the format and, if the name says so, the stage of a shader file.
+///
+/// `.glsl`, `.hlsl`, `.spv` and, with the `wgsl` feature, `.wgsl` can be prefixed by a stage suffix
+/// (`terrain.tesc.spv`), while a bare stage suffix (`terrain.tesc`) is taken to be GLSL.
+pub fn detectFormat(path: &Path) -> Result<(ShaderFileFormat, Option<ShaderStage>), ShaderCreateError> {
+    let unrecognized = || ShaderCreateError::UnrecognizedExtension(path.as_os_str().to_owned());
+
+    let extension = path.extension().ok_or_else(unrecognized)?;
+
+    let format = match extension.to_str() {
+        Some("glsl") => ShaderFileFormat::Glsl,
+        Some("hlsl") => ShaderFileFormat::Hlsl,
+        Some("spv") => ShaderFileFormat::Spirv,
+        #[cfg(feature = "wgsl")]
+        Some("wgsl") => ShaderFileFormat::Wgsl,
+        _ => {
+            let stage = ShaderStage::fromExtension(extension).ok_or_else(unrecognized)?;
+
+            return Ok((ShaderFileFormat::Glsl, Some(stage)));
+        }
+    };
+
+    let stage = path
+        .file_stem()
+        .map(Path::new)
+        .and_then(Path::extension)
+        .and_then(ShaderStage::fromExtension);
+
+    Ok((format, stage))
+}
+
+fn readSpirv(path: &Path, bytes: Vec<u8>) -> Result<ShaderData, ShaderCreateError> {
+    let magic = bytes
+        .get(..4)
+        .map(|magic| u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]));
+
+    if !bytes.len().is_multiple_of(4) || magic != Some(SPIRV_MAGIC) {
+        return Err(ShaderCreateError::InvalidSpirv(path.to_owned()));
+    }
+
+    Ok(ShaderData::Spirv(bytes))
+}
+
+impl ShaderCompileInfo {
+    /// Reads a single stage from disk, with `main` as the entry point.
+    ///
+    /// HLSL files get default `HlslOptions`; use `fromHlslFile` to pass your own.
+    ///
+    /// Fails if the file name carries a stage suffix that doesn't match `stage`.
+    pub fn fromFile<P: AsRef<Path>>(path: P, stage: ShaderStage) -> Result<Self, ShaderCreateError> {
+        let path = path.as_ref();
+
+        let (format, fileStage) = detectFormat(path)?;
+
+        if let Some(fileStage) = fileStage {
+            if fileStage != stage {
+                return Err(ShaderCreateError::StageMismatch(path.to_owned(), fileStage, stage));
+            }
+        }
+
+        let io = |err| ShaderCreateError::Io(path.to_owned(), err);
+
+        let data = match format {
+            ShaderFileFormat::Glsl => ShaderData::Source(std::fs::read_to_string(path).map_err(io)?),
+            ShaderFileFormat::Hlsl => ShaderData::Hlsl(
+                std::fs::read_to_string(path).map_err(io)?,
+                HlslOptions::default(),
+            ),
+            ShaderFileFormat::Spirv => readSpirv(path, std::fs::read(path).map_err(io)?)?,
+            #[cfg(feature = "wgsl")]
+            ShaderFileFormat::Wgsl => ShaderData::Wgsl(std::fs::read_to_string(path).map_err(io)?),
+        };
+
+        let mut info = Self::new("main", data);
+        info.sourcePath.replace(path.to_owned());
+
+        Ok(info)
+    }
+    /// A copy with the source read again from `sourcePath`, if there is one.
+    pub(crate) fn reloaded(&self) -> Result<Self, ShaderCreateError> {
+        let path = match &self.sourcePath {
+            Some(path) => path,
+            None => return Ok(self.clone()),
+        };
+
+        let io = |err| ShaderCreateError::Io(path.to_owned(), err);
+
+        let data = match &self.data {
+            ShaderData::Source(_) => ShaderData::Source(std::fs::read_to_string(path).map_err(io)?),
+            ShaderData::Hlsl(_, options) => {
+                ShaderData::Hlsl(std::fs::read_to_string(path).map_err(io)?, options.clone())
+            }
+            ShaderData::Spirv(_) => readSpirv(path, std::fs::read(path).map_err(io)?)?,
+            #[cfg(feature = "wgsl")]
+            ShaderData::Wgsl(_) => ShaderData::Wgsl(std::fs::read_to_string(path).map_err(io)?),
+        };
+
+        Ok(Self {
+            entryPoint: self.entryPoint.clone(),
+            data,
+            sourcePath: self.sourcePath.clone(),
+            includePaths: self.includePaths.clone(),
+            defines: self.defines.clone(),
+            options: self.options.clone(),
+        })
+    }
+    pub fn fromHlslFile<P: AsRef<Path>>(
+        path: P,
+        stage: ShaderStage,
+        entryPoint: &str,
+        options: HlslOptions,
+    ) -> Result<Self, ShaderCreateError> {
+        let mut info = Self::fromFile(path, stage)?.withEntryPoint(entryPoint);
+
+        if let ShaderData::Hlsl(_, hlslOptions) = &mut info.data {
+            *hlslOptions = options;
+        }
+
+        Ok(info)
+    }
+}
+
+impl ShaderStages {
+    /// Vertex and fragment stages from a single file with an entry point for each, e.g. a SPIR-V module with a
+    /// `vsMain` and an `fsMain`; a SPIR-V module backs both stages with one `vk::ShaderModule`.
+    pub fn fromModuleFile<P: AsRef<Path>>(
+        path: P,
+        vertexEntryPoint: &str,
+        fragmentEntryPoint: &str,
+    ) -> Result<Self, ShaderCreateError> {
+        let path = path.as_ref();
+
+        Ok(Self::new(
+            ShaderCompileInfo::fromFile(path, ShaderStage::Vertex)?.withEntryPoint(vertexEntryPoint),
+            ShaderCompileInfo::fromFile(path, ShaderStage::Fragment)?.withEntryPoint(fragmentEntryPoint),
+        ))
+    }
+}
+
+fn nameOf(path: &Path) -> String {
+    // Strip every extension, so that `water.vert.spv` becomes `water`.
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .and_then(|name| name.split('.').next())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+impl Shader {
+    /// Loads the vertex and fragment stages from disk, the shader is named after the vertex file.
+    pub fn fromFiles<P: AsRef<Path>, Q: AsRef<Path>>(
+        device: &Arc<crate::Device>,
+        vertexPath: P,
+        fragmentPath: Q,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let vertexPath = vertexPath.as_ref();
+
+        Self::create(
+            device,
+            nameOf(vertexPath),
+            ShaderCompileInfo::fromFile(vertexPath, ShaderStage::Vertex)?,
+            ShaderCompileInfo::fromFile(fragmentPath, ShaderStage::Fragment)?,
+        )
+    }
+}
+
+impl ComputeShader {
+    pub fn fromFile<P: AsRef<Path>>(
+        device: &Arc<crate::Device>,
+        path: P,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+
+        Self::create(
+            device,
+            nameOf(path),
+            ShaderCompileInfo::fromFile(path, ShaderStage::Compute)?,
+        )
+    }
+}
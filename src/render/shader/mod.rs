@@ -1,101 +1,612 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::{OsStr, OsString},
+    hash::{Hash, Hasher},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
 };
 
 use ash::{version::DeviceV1_0, vk};
+#[cfg(feature = "shaderc")]
+use std::cell::RefCell;
+#[cfg(feature = "shaderc")]
+use self::diagnostics::Severity;
 
-mod reflection;
+mod block;
+pub mod codegen;
+mod datatype;
+pub mod diagnostics;
+pub mod diskcache;
+mod include;
+mod interface;
+pub mod batch;
+pub mod loader;
+pub mod permutations;
+pub mod reflection;
+pub mod registry;
+pub mod watcher;
+#[cfg(feature = "wgsl")]
+mod wgsl;
 
 use c_str_macro::c_str;
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum ShaderCreateError {
-    #[error("Couldn't recognise shader format for: {0:?}!\n .glsl -> GLSL shader\n .hlsl -> HLSL shader\n .spv -> SPIR-V blob")]
+    #[error("Couldn't recognise shader format for: {0:?}!\n .glsl -> GLSL shader\n .hlsl -> HLSL shader\n .spv -> SPIR-V blob\n .wgsl -> WGSL shader (with the wgsl feature)\n .vert/.tesc/.tese/.geom/.frag/.comp -> GLSL shader for that stage")]
     UnrecognizedExtension(OsString),
-    #[error("Failed to compile shader: {0:?}")]
-    CompilationError(OsString),
+    #[error("Failed to read shader {0:?}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("{0:?} is not a valid SPIR-V module!")]
+    InvalidSpirv(PathBuf),
+    #[error("{0:?} is a {1:?} shader, but was loaded as a {2:?} shader!")]
+    StageMismatch(PathBuf, ShaderStage, ShaderStage),
+    #[error("No shader named {0:?} was registered for permutations!")]
+    UnknownShader(String),
+    #[error("Shader {0:?}: fragment input {1:?} at location {2} has no matching output in the {3:?} stage!")]
+    MissingStageOutput(String, String, u32, ShaderStage),
+    #[error("Shader {0:?}: {1:?} output {2:?} is {3:?}, which can't feed fragment input {4:?} of type {5:?} at location {6}!")]
+    InterfaceTypeMismatch(String, ShaderStage, String, ShaderDataType, String, ShaderDataType, u32),
+    #[error("Shader {0:?}: set {1} binding {2} is a {3:?} in the {4:?} stage but a {5:?} in the {6:?} stage!")]
+    BindingTypeMismatch(String, u32, u32, DescriptorType, ShaderStage, DescriptorType, ShaderStage),
+    #[error("Shader {0:?} is {1:?} source, but the shaderc feature is disabled; only SPIR-V can be loaded!")]
+    CompilerUnavailable(String, SourceLanguage),
+    #[error("Failed to create shaderc compile options!")]
+    CompileOptionsUnavailable,
+    #[error("Failed to compile shader {0:?}:\n{}", diagnostics::formatDiagnostics(.1))]
+    CompilationError(String, Vec<Diagnostic>),
+    #[error("SPIR-V {0:?} is newer than {1:?} supports!")]
+    UnsupportedSpirvVersion(SpirvVersion, VulkanVersion),
+    #[error("Shader {0:?} has no {2:?} entry point named {1:?}, the module declares: {3}")]
+    MissingEntryPoint(String, String, ShaderStage, String),
+    #[error("The module has no {1:?} entry point named {0:?} to reflect!")]
+    UnknownEntryPoint(String, ShaderStage),
+    #[error("WGSL has no {0:?} stage!")]
+    UnsupportedWgslStage(ShaderStage),
+    #[error("Shader {0:?} has only one of the tessellation control and evaluation stages, both are required!")]
+    IncompleteTessellation(String),
 }
+/// Maps an HLSL register (`b0`, `t3`, ...) to a descriptor set and binding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HlslRegisterBinding {
+    pub register: String,
+    pub set: u32,
+    pub binding: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct HlslOptions {
+    /// Explicit register mappings, registers that aren't listed keep their number as the binding in set 0.
+    pub registerBindings: Vec<HlslRegisterBinding>,
+    /// Assigns bindings to resources that don't have a register.
+    pub autoBindUniforms: bool,
+    /// Maps HLSL `register(...)` annotations to bindings at all; without it they're ignored.
+    pub ioMapping: bool,
+    /// Uses HLSL packing rules for constant buffer member offsets.
+    pub offsets: bool,
+}
+impl HlslOptions {
+    pub fn bindRegister(&mut self, register: &str, set: u32, binding: u32) -> &mut Self {
+        self.registerBindings.push(HlslRegisterBinding {
+            register: register.to_owned(),
+            set,
+            binding,
+        });
+
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceLanguage {
+    Glsl,
+    Hlsl,
+    #[cfg(feature = "wgsl")]
+    Wgsl,
+}
+
+#[derive(Clone)]
 pub enum ShaderData {
+    /// GLSL source, compiled by shaderc; needs the shaderc feature.
     Source(String),
+    /// HLSL source, compiled by shaderc; needs the shaderc feature.
+    Hlsl(String, HlslOptions),
     Spirv(Vec<u8>),
+    /// WGSL source, translated to SPIR-V by naga.
+    #[cfg(feature = "wgsl")]
+    Wgsl(String),
+}
+impl ShaderData {
+    /// Language of the source, `None` for precompiled SPIR-V.
+    pub fn language(&self) -> Option<SourceLanguage> {
+        match self {
+            Self::Source(_) => Some(SourceLanguage::Glsl),
+            Self::Hlsl(..) => Some(SourceLanguage::Hlsl),
+            Self::Spirv(_) => None,
+            #[cfg(feature = "wgsl")]
+            Self::Wgsl(_) => Some(SourceLanguage::Wgsl),
+        }
+    }
+    /// The source text or SPIR-V, as it's handed to the compiler.
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Source(text) | Self::Hlsl(text, _) => text.as_bytes(),
+            Self::Spirv(bytes) => bytes,
+            #[cfg(feature = "wgsl")]
+            Self::Wgsl(text) => text.as_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+impl ShaderStage {
+    /// The conventional file extension for GLSL sources of this stage, as used by glslang.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Vertex => "vert",
+            Self::TessellationControl => "tesc",
+            Self::TessellationEvaluation => "tese",
+            Self::Geometry => "geom",
+            Self::Fragment => "frag",
+            Self::Compute => "comp",
+        }
+    }
+    pub fn fromExtension(extension: &OsStr) -> Option<Self> {
+        [
+            Self::Vertex,
+            Self::TessellationControl,
+            Self::TessellationEvaluation,
+            Self::Geometry,
+            Self::Fragment,
+            Self::Compute,
+        ]
+        .iter()
+        .copied()
+        .find(|stage| extension == stage.extension())
+    }
+}
+#[cfg(feature = "shaderc")]
+impl Into<shaderc::ShaderKind> for ShaderStage {
+    fn into(self) -> shaderc::ShaderKind {
+        match self {
+            Self::Vertex => shaderc::ShaderKind::Vertex,
+            Self::TessellationControl => shaderc::ShaderKind::TessControl,
+            Self::TessellationEvaluation => shaderc::ShaderKind::TessEvaluation,
+            Self::Geometry => shaderc::ShaderKind::Geometry,
+            Self::Fragment => shaderc::ShaderKind::Fragment,
+            Self::Compute => shaderc::ShaderKind::Compute,
+        }
+    }
+}
+impl Into<vk::ShaderStageFlags> for ShaderStage {
+    fn into(self) -> vk::ShaderStageFlags {
+        match self {
+            Self::Vertex => vk::ShaderStageFlags::VERTEX,
+            Self::TessellationControl => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+            Self::TessellationEvaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            Self::Geometry => vk::ShaderStageFlags::GEOMETRY,
+            Self::Fragment => vk::ShaderStageFlags::FRAGMENT,
+            Self::Compute => vk::ShaderStageFlags::COMPUTE,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub enum ShaderDataType {
-    Int,
-    UInt,
-    Float,
-    Vec2f,
-    Vec3f,
-    Vec4f,
+/// Preprocessor macros, kept sorted so that equal sets compare and hash equal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ShaderDefines {
+    defines: BTreeMap<String, Option<String>>,
 }
-use spirv_reflect::types::ReflectFormat;
+impl ShaderDefines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// `#define name`, or `#define name value` if a value is given.
+    pub fn define(&mut self, name: &str, value: Option<&str>) -> &mut Self {
+        self.defines
+            .insert(name.to_owned(), value.map(str::to_owned));
+
+        self
+    }
+    pub fn isEmpty(&self) -> bool {
+        self.defines.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_deref()))
+    }
+    /// Adds every define of `other`, overriding values of defines present in both.
+    pub fn extend(&mut self, other: &ShaderDefines) -> &mut Self {
+        self.defines.extend(
+            other
+                .defines
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
 
-impl From<ReflectFormat> for ShaderDataType {
-    fn from(x: ReflectFormat) -> Self {
-        match x {
-            ReflectFormat::R32_SFLOAT => Self::Float,
-            ReflectFormat::R32G32B32_SFLOAT => Self::Vec3f,
-            ReflectFormat::R32G32B32A32_SFLOAT => Self::Vec4f,
-            ReflectFormat::R32_SINT => Self::Int,
-            ReflectFormat::R32_UINT => Self::UInt,
-            _ => todo!(),
+        self
+    }
+}
+impl std::fmt::Display for ShaderDefines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let defines: Vec<_> = self
+            .iter()
+            .map(|(name, value)| match value {
+                Some(value) => format!("{}={}", name, value),
+                None => name.to_owned(),
+            })
+            .collect();
+
+        write!(f, "[{}]", defines.join(","))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptimizationLevel {
+    Zero,
+    Size,
+    Performance,
+}
+#[cfg(feature = "shaderc")]
+impl Into<shaderc::OptimizationLevel> for OptimizationLevel {
+    fn into(self) -> shaderc::OptimizationLevel {
+        match self {
+            Self::Zero => shaderc::OptimizationLevel::Zero,
+            Self::Size => shaderc::OptimizationLevel::Size,
+            Self::Performance => shaderc::OptimizationLevel::Performance,
         }
     }
 }
 
-pub struct ShaderCompileInfo {
-    pub(crate) entryPoint: String,
-    pub(crate) data: ShaderData,
+/// Vulkan version the SPIR-V is compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VulkanVersion {
+    Vulkan1_0,
+    Vulkan1_1,
+    Vulkan1_2,
 }
-struct ShaderCompiler {
-    inner: shaderc::Compiler,
+impl VulkanVersion {
+    /// Newest SPIR-V version the environment is required to consume.
+    pub fn maxSpirvVersion(&self) -> SpirvVersion {
+        match self {
+            Self::Vulkan1_0 => SpirvVersion::V1_0,
+            Self::Vulkan1_1 => SpirvVersion::V1_3,
+            Self::Vulkan1_2 => SpirvVersion::V1_5,
+        }
+    }
 }
-impl ShaderCompiler {
-    fn new() -> ShaderCompiler {
-        ShaderCompiler {
-            inner: shaderc::Compiler::new().unwrap(),
+#[cfg(feature = "shaderc")]
+impl Into<shaderc::EnvVersion> for VulkanVersion {
+    fn into(self) -> shaderc::EnvVersion {
+        match self {
+            Self::Vulkan1_0 => shaderc::EnvVersion::Vulkan1_0,
+            Self::Vulkan1_1 => shaderc::EnvVersion::Vulkan1_1,
+            Self::Vulkan1_2 => shaderc::EnvVersion::Vulkan1_2,
         }
     }
 }
-unsafe impl Send for ShaderCompiler {}
 
-use std::ops::Deref;
-impl Deref for ShaderCompiler {
-    type Target = shaderc::Compiler;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpirvVersion {
+    V1_0,
+    V1_1,
+    V1_2,
+    V1_3,
+    V1_4,
+    V1_5,
+}
+#[cfg(feature = "shaderc")]
+impl Into<shaderc::SpirvVersion> for SpirvVersion {
+    fn into(self) -> shaderc::SpirvVersion {
+        match self {
+            Self::V1_0 => shaderc::SpirvVersion::V1_0,
+            Self::V1_1 => shaderc::SpirvVersion::V1_1,
+            Self::V1_2 => shaderc::SpirvVersion::V1_2,
+            Self::V1_3 => shaderc::SpirvVersion::V1_3,
+            Self::V1_4 => shaderc::SpirvVersion::V1_4,
+            Self::V1_5 => shaderc::SpirvVersion::V1_5,
+        }
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+/// Compiler settings, either for a single shader stage or for everything through `setDefaultCompileOptions`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderCompileOptions {
+    pub optimization: OptimizationLevel,
+    /// Keeps names and source lines in the SPIR-V, for source-level debugging in RenderDoc.
+    pub debugInfo: bool,
+    pub targetEnvironment: VulkanVersion,
+    /// `None` uses the SPIR-V version the target environment defaults to.
+    pub spirvVersion: Option<SpirvVersion>,
+    pub warningsAsErrors: bool,
+}
+impl Default for ShaderCompileOptions {
+    fn default() -> Self {
+        Self {
+            optimization: OptimizationLevel::Zero,
+            debugInfo: false,
+            targetEnvironment: VulkanVersion::Vulkan1_0,
+            spirvVersion: None,
+            warningsAsErrors: false,
+        }
     }
 }
-use std::ops::DerefMut;
+impl ShaderCompileOptions {
+    /// Unoptimized SPIR-V with debug info.
+    pub fn debug() -> Self {
+        Self {
+            debugInfo: true,
+            ..Self::default()
+        }
+    }
+    /// SPIR-V optimized for performance, without debug info.
+    pub fn release() -> Self {
+        Self {
+            optimization: OptimizationLevel::Performance,
+            ..Self::default()
+        }
+    }
+    #[cfg(feature = "shaderc")]
+    fn apply(&self, options: &mut shaderc::CompileOptions) -> Result<(), ShaderCreateError> {
+        if let Some(spirvVersion) = self.spirvVersion {
+            if spirvVersion > self.targetEnvironment.maxSpirvVersion() {
+                return Err(ShaderCreateError::UnsupportedSpirvVersion(spirvVersion, self.targetEnvironment));
+            }
+
+            options.set_target_spirv(spirvVersion.into());
+        }
+
+        let environment: shaderc::EnvVersion = self.targetEnvironment.into();
+        options.set_target_env(shaderc::TargetEnv::Vulkan, environment as u32);
+
+        options.set_optimization_level(self.optimization.into());
+
+        if self.debugInfo {
+            options.set_generate_debug_info();
+        }
+
+        if self.warningsAsErrors {
+            options.set_warnings_as_errors();
+        }
 
-use self::reflection::{reflectShader, ReflectionData};
-impl DerefMut for ShaderCompiler {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
+        Ok(())
     }
 }
+
 lazy_static! {
-    static ref COMPILER: Mutex<ShaderCompiler> = Mutex::new(ShaderCompiler::new());
+    static ref DEFAULT_COMPILE_OPTIONS: RwLock<ShaderCompileOptions> = RwLock::new(ShaderCompileOptions::default());
 }
+
+/// Options for every shader stage that doesn't set its own, e.g. `ShaderCompileOptions::release()` in release builds.
+pub fn setDefaultCompileOptions(options: ShaderCompileOptions) {
+    *DEFAULT_COMPILE_OPTIONS.write().unwrap() = options;
+}
+
+#[derive(Clone)]
+pub struct ShaderCompileInfo {
+    pub(crate) entryPoint: String,
+    pub(crate) data: ShaderData,
+    /// File the source was read from, relative includes are resolved next to it.
+    pub(crate) sourcePath: Option<PathBuf>,
+    pub(crate) includePaths: Vec<PathBuf>,
+    pub(crate) defines: ShaderDefines,
+    /// `None` uses the global default options.
+    pub(crate) options: Option<ShaderCompileOptions>,
+}
+impl ShaderCompileInfo {
+    pub fn new(entryPoint: &str, data: ShaderData) -> Self {
+        Self {
+            entryPoint: entryPoint.to_owned(),
+            data,
+            sourcePath: None,
+            includePaths: Vec::new(),
+            defines: ShaderDefines::new(),
+            options: None,
+        }
+    }
+    /// Passed to the compiler as `#define name value`; ignored for SPIR-V.
+    pub fn define(mut self, name: &str, value: Option<&str>) -> Self {
+        self.defines.define(name, value);
+
+        self
+    }
+    pub fn defines(mut self, defines: &ShaderDefines) -> Self {
+        self.defines.extend(defines);
+
+        self
+    }
+    /// Adds a directory to search for `#include`d files, searched in the order they were added.
+    pub fn includePath<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.includePaths.push(path.into());
+
+        self
+    }
+    /// HLSL entry points are usually named after the stage, e.g. `VSMain`.
+    pub fn withEntryPoint(mut self, entryPoint: &str) -> Self {
+        self.entryPoint = entryPoint.to_owned();
+
+        self
+    }
+    pub fn entryPoint(&self) -> &str {
+        &self.entryPoint
+    }
+    /// Overrides the global default options for this stage.
+    pub fn options(mut self, options: ShaderCompileOptions) -> Self {
+        self.options.replace(options);
+
+        self
+    }
+    /// The options this stage is compiled with.
+    pub fn effectiveOptions(&self) -> ShaderCompileOptions {
+        match &self.options {
+            Some(options) => options.clone(),
+            None => DEFAULT_COMPILE_OPTIONS.read().unwrap().clone(),
+        }
+    }
+    /// Compiles to SPIR-V the way shader creation does, but without a device, e.g. for offline tools.
+    ///
+    /// Returns the SPIR-V along with every file it was built from. Without a device to check against, WGSL
+    /// shaders may use every capability naga knows.
+    pub fn compile(&self, name: &str, stage: ShaderStage) -> Result<(Vec<u8>, Vec<PathBuf>), CompileError> {
+        processShader(&name.to_owned(), self, stage, None)
+    }
+}
+pub use self::block::{BlockField, ShaderBlock, ShaderBlockError};
+pub use self::datatype::{MemoryLayout, ScalarType, ShaderDataType, ShaderDataTypeError};
+
+use self::{
+    diagnostics::Diagnostic,
+    include::IncludeResolver,
+    reflection::{reflectEntryPoint, DescriptorType, ReflectionData},
+};
+#[cfg(feature = "shaderc")]
+thread_local! {
+    /// One compiler per thread, so that compilations on different threads don't wait for each other.
+    static COMPILER: RefCell<shaderc::Compiler> = RefCell::new(shaderc::Compiler::new().unwrap());
+}
+/// Errors of the compilation steps, which have to be `Send` to come back from `compileAll`'s worker threads.
+pub type CompileError = Box<dyn std::error::Error + Send + Sync>;
+
+fn boxed(err: CompileError) -> Box<dyn std::error::Error> {
+    err
+}
+/// Compiles (if needed) a stage to SPIR-V, returning it along with every file it was built from.
+///
+/// `features` are the device features WGSL shaders are validated against, `None` when compiling offline.
 fn processShader(
     name: &String,
     compileInfo: &ShaderCompileInfo,
-    kind: shaderc::ShaderKind,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    use shaderc::*;
+    stage: ShaderStage,
+    features: Option<&vk::PhysicalDeviceFeatures>,
+) -> Result<(Vec<u8>, Vec<PathBuf>), CompileError> {
+    let sourceDependency = || {
+        compileInfo
+            .sourcePath
+            .iter()
+            .map(|path| path.canonicalize().unwrap_or_else(|_| path.to_owned()))
+    };
+
+    if let ShaderData::Spirv(bytes) = &compileInfo.data {
+        return Ok((bytes.clone(), sourceDependency().collect()));
+    }
+
+    diskcache::cached(compileInfo, stage, features, || {
+        let resolver = IncludeResolver::new(&compileInfo.includePaths, compileInfo.sourcePath.as_deref());
+
+        let byteCode = compileSource(name, compileInfo, stage, features, &resolver)?;
+
+        // Hashes of what was compiled rather than of the files now, which may have been saved again since.
+        let sourceHash = diskcache::hashBytes(compileInfo.data.bytes());
+
+        Ok((
+            byteCode,
+            sourceDependency()
+                .map(|path| (path, sourceHash))
+                .chain(resolver.intoDependencies())
+                .collect(),
+        ))
+    })
+}
+// Only WGSL needs the device features, and without a compiler only WGSL gets as far as needing the stage and
+// the include resolver.
+#[cfg_attr(not(all(feature = "shaderc", feature = "wgsl")), allow(unused_variables))]
+fn compileSource(
+    name: &String,
+    compileInfo: &ShaderCompileInfo,
+    stage: ShaderStage,
+    features: Option<&vk::PhysicalDeviceFeatures>,
+    resolver: &IncludeResolver,
+) -> Result<Vec<u8>, CompileError> {
     let byteCode = match &compileInfo.data {
+        #[cfg(feature = "shaderc")]
         ShaderData::Source(text) => {
-            compileShader(&name, &compileInfo.entryPoint, &text, kind)?
+            let options = compileOptions(compileInfo, resolver)?;
+
+            compileShader(&sourceName(name, compileInfo), &compileInfo.entryPoint, &text, stage.into(), Some(&options), resolver)?
+        }
+        #[cfg(feature = "shaderc")]
+        ShaderData::Hlsl(text, hlslOptions) => {
+            let mut options = compileOptions(compileInfo, resolver)?;
+            applyHlslOptions(&mut options, hlslOptions);
+
+            compileShader(&sourceName(name, compileInfo), &compileInfo.entryPoint, &text, stage.into(), Some(&options), resolver)?
+        }
+        #[cfg(not(feature = "shaderc"))]
+        ShaderData::Source(_) | ShaderData::Hlsl(..) => {
+            let language = compileInfo.data.language().unwrap();
+
+            return Err(Box::new(ShaderCreateError::CompilerUnavailable(name.to_owned(), language)));
         }
         ShaderData::Spirv(bytes) => bytes.clone(),
+        #[cfg(feature = "wgsl")]
+        ShaderData::Wgsl(text) => wgsl::translate(
+            &sourceName(name, compileInfo),
+            compileInfo.sourcePath.clone(),
+            text,
+            &compileInfo.entryPoint,
+            stage,
+            wgsl::capabilities(features),
+        )?,
     };
 
     Ok(byteCode)
 }
+/// Name the compiler sees for the main source file, which is where relative includes start from.
+#[cfg(any(feature = "shaderc", feature = "wgsl"))]
+fn sourceName(name: &String, compileInfo: &ShaderCompileInfo) -> String {
+    match &compileInfo.sourcePath {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => name.to_owned(),
+    }
+}
+#[cfg(feature = "shaderc")]
+fn compileOptions<'a>(
+    compileInfo: &ShaderCompileInfo,
+    resolver: &'a IncludeResolver,
+) -> Result<shaderc::CompileOptions<'a>, ShaderCreateError> {
+    let mut options =
+        shaderc::CompileOptions::new().ok_or(ShaderCreateError::CompileOptionsUnavailable)?;
+
+    if compileInfo.data.language() == Some(SourceLanguage::Hlsl) {
+        options.set_source_language(shaderc::SourceLanguage::HLSL);
+    }
+
+    compileInfo.effectiveOptions().apply(&mut options)?;
+
+    for (name, value) in compileInfo.defines.iter() {
+        options.add_macro_definition(name, value);
+    }
+
+    options.set_include_callback(move |requested, includeType, requestingSource, _depth| {
+        resolver.resolve(requested, includeType, requestingSource)
+    });
+
+    Ok(options)
+}
+#[cfg(feature = "shaderc")]
+fn applyHlslOptions(options: &mut shaderc::CompileOptions, hlslOptions: &HlslOptions) {
+    options.set_auto_bind_uniforms(hlslOptions.autoBindUniforms);
+    options.set_hlsl_io_mapping(hlslOptions.ioMapping);
+    options.set_hlsl_offsets(hlslOptions.offsets);
+
+    for binding in &hlslOptions.registerBindings {
+        options.set_hlsl_register_set_and_binding(
+            &binding.register,
+            &binding.set.to_string(),
+            &binding.binding.to_string(),
+        );
+    }
+}
 fn createShaderModule(
     device: &Arc<super::Device>,
     byteCode: &[u8],
@@ -111,15 +622,32 @@ fn createShaderModule(
 
     Ok(unsafe { device.raw().create_shader_module(&shaderCreateInfo, None)? })
 }
+/// Compiles with the shared compiler, reporting errors and warnings to the diagnostic sink.
+#[cfg(feature = "shaderc")]
 fn compileShader(
     name: &str,
     entryPoint: &str,
     text: &String,
     kind: shaderc::ShaderKind,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut compiler = COMPILER.lock().unwrap();
+    options: Option<&shaderc::CompileOptions>,
+    resolver: &IncludeResolver,
+) -> Result<Vec<u8>, CompileError> {
+    let result = COMPILER.with(|compiler| {
+        compiler
+            .borrow_mut()
+            .compile_into_spirv(text.as_str(), kind, name, entryPoint, options)
+    });
 
-    let artifact = compiler.compile_into_spirv(text.as_str(), kind, name, entryPoint, None)?;
+    let artifact = match result {
+        Ok(artifact) => artifact,
+        Err(shaderc::Error::CompilationError(_, messages)) => {
+            let diagnostics = diagnostics::parse(&messages, |file| resolver.pathOf(file));
+            diagnostics::report(name, &diagnostics);
+
+            return Err(Box::new(ShaderCreateError::CompilationError(name.to_owned(), diagnostics)));
+        }
+        Err(err) => return Err(Box::new(err)),
+    };
 
     if artifact.get_num_warnings() > 0 {
         log::warn!(
@@ -128,21 +656,335 @@ fn compileShader(
             kind,
             artifact.get_warning_messages()
         );
+
+        let warnings = diagnostics::parse(&artifact.get_warning_messages(), |file| resolver.pathOf(file));
+
+        // Lines of the warning output without a severity marker are still warnings.
+        let warnings: Vec<_> = warnings
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                severity: match diagnostic.severity {
+                    Severity::Error => Severity::Warning,
+                    severity => severity,
+                },
+                ..diagnostic
+            })
+            .collect();
+
+        diagnostics::report(name, &warnings);
     }
 
     Ok(artifact.as_binary_u8().to_owned())
 }
 pub struct ShaderInfo {
+    pub(crate) stage: ShaderStage,
     pub(crate) module: vk::ShaderModule,
     pub(crate) compileInfo: ShaderCompileInfo,
     pub(crate) reflectionData: ReflectionData,
+    pub(crate) dependencies: Vec<PathBuf>,
 }
-pub struct Shader {
+/// Modules created so far for a shader, by SPIR-V, so that stages backed by the same multi-entry-point
+/// module share a single `vk::ShaderModule`.
+type SharedModules = HashMap<Vec<u8>, vk::ShaderModule>;
+
+impl ShaderInfo {
+    fn create(
+        device: &Arc<super::Device>,
+        compiled: CompiledStage,
+        shared: &mut SharedModules,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        reflection::validateEntryPoint(
+            &compiled.name,
+            &compiled.bytes,
+            &compiled.compileInfo.entryPoint,
+            compiled.stage,
+        )?;
+
+        let reflectionData = reflectEntryPoint(&compiled.bytes, &compiled.compileInfo.entryPoint, compiled.stage)?;
+
+        let module = match shared.get(&compiled.bytes) {
+            Some(&module) => module,
+            None => {
+                let module = createShaderModule(device, &compiled.bytes)?;
+                shared.insert(compiled.bytes, module);
+
+                module
+            }
+        };
+
+        Ok(Self {
+            stage: compiled.stage,
+            module,
+            compileInfo: compiled.compileInfo,
+            reflectionData,
+            dependencies: compiled.dependencies,
+        })
+    }
+    /// Source file and everything it includes; empty for shaders that weren't loaded from disk.
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+}
+/// Sources for every stage of a graphics shader; the optional ones are skipped when `None`.
+#[derive(Clone)]
+pub struct ShaderStages {
+    pub vertex: ShaderCompileInfo,
+    pub tessellationControl: Option<ShaderCompileInfo>,
+    pub tessellationEvaluation: Option<ShaderCompileInfo>,
+    pub geometry: Option<ShaderCompileInfo>,
+    pub fragment: ShaderCompileInfo,
+}
+impl ShaderStages {
+    pub fn new(vertex: ShaderCompileInfo, fragment: ShaderCompileInfo) -> Self {
+        Self {
+            vertex,
+            tessellationControl: None,
+            tessellationEvaluation: None,
+            geometry: None,
+            fragment,
+        }
+    }
+    /// Adds `defines` to every stage.
+    pub fn defines(self, defines: &ShaderDefines) -> Self {
+        let apply = |info: Option<ShaderCompileInfo>| info.map(|info| info.defines(defines));
+
+        Self {
+            vertex: self.vertex.defines(defines),
+            tessellationControl: apply(self.tessellationControl),
+            tessellationEvaluation: apply(self.tessellationEvaluation),
+            geometry: apply(self.geometry),
+            fragment: self.fragment.defines(defines),
+        }
+    }
+}
+
+/// Compiled stages of a `Shader`, replaced as a whole when the shader is reloaded.
+///
+/// Pipelines only need the modules while they're being created, so an outdated set is destroyed as soon as
+/// nothing is building a pipeline from it anymore.
+pub struct ShaderModules {
     device: Arc<super::Device>,
-    pub(crate) name: String,
+    generation: u64,
     pub(crate) vertex: ShaderInfo,
+    pub(crate) tessellationControl: Option<ShaderInfo>,
+    pub(crate) tessellationEvaluation: Option<ShaderInfo>,
+    pub(crate) geometry: Option<ShaderInfo>,
     pub(crate) fragment: ShaderInfo,
 }
+/// SPIR-V of a single stage, before it's turned into a module.
+struct CompiledStage {
+    name: String,
+    stage: ShaderStage,
+    compileInfo: ShaderCompileInfo,
+    bytes: Vec<u8>,
+    dependencies: Vec<PathBuf>,
+}
+impl CompiledStage {
+    fn compile(
+        name: &String,
+        compileInfo: ShaderCompileInfo,
+        stage: ShaderStage,
+        features: Option<&vk::PhysicalDeviceFeatures>,
+    ) -> Result<Self, CompileError> {
+        let (bytes, dependencies) = processShader(name, &compileInfo, stage, features)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            stage,
+            compileInfo,
+            bytes,
+            dependencies,
+        })
+    }
+}
+
+/// What a shader is registered under in the `ShaderRegistry`, along with its name.
+fn hashStages<'a>(stages: impl Iterator<Item = &'a CompiledStage>) -> u64 {
+    let mut hasher = diskcache::FnvHasher::default();
+
+    for stage in stages {
+        stage.stage.hash(&mut hasher);
+        stage.bytes.hash(&mut hasher);
+        // One multi-entry-point module can back different shaders.
+        stage.compileInfo.entryPoint.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// Every stage of a graphics shader compiled to SPIR-V, so that it can be looked up in the `ShaderRegistry`
+/// before any modules are created.
+pub(crate) struct CompiledStages {
+    vertex: CompiledStage,
+    tessellationControl: Option<CompiledStage>,
+    tessellationEvaluation: Option<CompiledStage>,
+    geometry: Option<CompiledStage>,
+    fragment: CompiledStage,
+}
+impl CompiledStages {
+    pub(crate) fn compile(
+        name: &String,
+        stages: ShaderStages,
+        features: Option<&vk::PhysicalDeviceFeatures>,
+    ) -> Result<Self, CompileError> {
+        if stages.tessellationControl.is_some() != stages.tessellationEvaluation.is_some() {
+            return Err(Box::new(ShaderCreateError::IncompleteTessellation(name.to_owned())));
+        }
+
+        let compileStage = |info: Option<ShaderCompileInfo>, suffix: &str, kind| {
+            info.map(|info| CompiledStage::compile(&(name.to_owned() + suffix), info, kind, features))
+                .transpose()
+        };
+
+        Ok(Self {
+            vertex: CompiledStage::compile(
+                &(name.to_owned() + "_vertex"),
+                stages.vertex,
+                ShaderStage::Vertex,
+                features,
+            )?,
+            tessellationControl: compileStage(
+                stages.tessellationControl,
+                "_tesscontrol",
+                ShaderStage::TessellationControl,
+            )?,
+            tessellationEvaluation: compileStage(
+                stages.tessellationEvaluation,
+                "_tesseval",
+                ShaderStage::TessellationEvaluation,
+            )?,
+            geometry: compileStage(stages.geometry, "_geometry", ShaderStage::Geometry)?,
+            fragment: CompiledStage::compile(
+                &(name.to_owned() + "_fragment"),
+                stages.fragment,
+                ShaderStage::Fragment,
+                features,
+            )?,
+        })
+    }
+    /// Hash of the SPIR-V and entry point of every stage, in pipeline order.
+    pub(crate) fn hash(&self) -> u64 {
+        let stages = [
+            Some(&self.vertex),
+            self.tessellationControl.as_ref(),
+            self.tessellationEvaluation.as_ref(),
+            self.geometry.as_ref(),
+            Some(&self.fragment),
+        ];
+
+        hashStages(stages.iter().flatten().copied())
+    }
+}
+
+impl ShaderModules {
+    fn fromCompiled(
+        device: &Arc<super::Device>,
+        name: &String,
+        compiled: CompiledStages,
+        generation: u64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut shared = SharedModules::new();
+
+        let modules = match Self::createStages(device, compiled, generation, &mut shared) {
+            Ok(modules) => modules,
+            Err(err) => {
+                // Nothing owns the modules of the stages before the one that failed yet.
+                for &module in shared.values() {
+                    unsafe { device.raw().destroy_shader_module(module, None) };
+                }
+
+                return Err(err);
+            }
+        };
+
+        interface::validate(name, &modules.stages())?;
+
+        Ok(modules)
+    }
+    fn createStages(
+        device: &Arc<super::Device>,
+        compiled: CompiledStages,
+        generation: u64,
+        shared: &mut SharedModules,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut createStage = |compiled: Option<CompiledStage>| {
+            compiled
+                .map(|compiled| ShaderInfo::create(device, compiled, shared))
+                .transpose()
+        };
+
+        Ok(Self {
+            device: device.clone(),
+            generation,
+            vertex: createStage(Some(compiled.vertex))?.unwrap(),
+            tessellationControl: createStage(compiled.tessellationControl)?,
+            tessellationEvaluation: createStage(compiled.tessellationEvaluation)?,
+            geometry: createStage(compiled.geometry)?,
+            fragment: createStage(Some(compiled.fragment))?.unwrap(),
+        })
+    }
+    /// Every present stage in pipeline order.
+    pub(crate) fn stages(&self) -> Vec<(vk::ShaderStageFlags, &ShaderInfo)> {
+        let optional = [
+            (vk::ShaderStageFlags::TESSELLATION_CONTROL, &self.tessellationControl),
+            (vk::ShaderStageFlags::TESSELLATION_EVALUATION, &self.tessellationEvaluation),
+            (vk::ShaderStageFlags::GEOMETRY, &self.geometry),
+        ];
+
+        std::iter::once((vk::ShaderStageFlags::VERTEX, &self.vertex))
+            .chain(
+                optional
+                    .iter()
+                    .filter_map(|&(stage, info)| info.as_ref().map(|info| (stage, info))),
+            )
+            .chain(std::iter::once((vk::ShaderStageFlags::FRAGMENT, &self.fragment)))
+            .collect()
+    }
+    /// Sources of every stage, re-read from disk where they came from a file.
+    fn reloadedSources(&self) -> Result<ShaderStages, ShaderCreateError> {
+        let reload = |info: &Option<ShaderInfo>| {
+            info.as_ref()
+                .map(|info| info.compileInfo.reloaded())
+                .transpose()
+        };
+
+        Ok(ShaderStages {
+            vertex: self.vertex.compileInfo.reloaded()?,
+            tessellationControl: reload(&self.tessellationControl)?,
+            tessellationEvaluation: reload(&self.tessellationEvaluation)?,
+            geometry: reload(&self.geometry)?,
+            fragment: self.fragment.compileInfo.reloaded()?,
+        })
+    }
+    /// Incremented every time the shader is reloaded.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    pub fn hasTessellation(&self) -> bool {
+        self.tessellationControl.is_some()
+    }
+    pub fn hasGeometry(&self) -> bool {
+        self.geometry.is_some()
+    }
+}
+impl Drop for ShaderModules {
+    fn drop(&mut self) {
+        // Stages can share a module, which must only be destroyed once.
+        let modules: HashSet<_> = self.stages().iter().map(|(_, info)| info.module).collect();
+
+        for module in modules {
+            unsafe { self.device.raw().destroy_shader_module(module, None) };
+        }
+    }
+}
+
+pub struct Shader {
+    device: Arc<super::Device>,
+    pub(crate) name: String,
+    /// SPIR-V hash the shader is registered under in the device's `ShaderRegistry`.
+    spirvHash: AtomicU64,
+    modules: RwLock<Arc<ShaderModules>>,
+}
 
 impl Shader {
     pub fn create(
@@ -151,47 +993,242 @@ impl Shader {
         vertexInfo: ShaderCompileInfo,
         fragmentInfo: ShaderCompileInfo,
     ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
-        let vertexBytes = processShader(&(name.to_owned() + "_vertex"), &vertexInfo, shaderc::ShaderKind::Vertex)?;
-        let fragmentBytes = processShader(&(name.to_owned() +"_fragment"),&fragmentInfo, shaderc::ShaderKind::Fragment)?;
+        Self::createWithStages(device, name, ShaderStages::new(vertexInfo, fragmentInfo))
+    }
+    /// Compiles every stage, then hands out the already loaded shader if one with the same name and SPIR-V is
+    /// still alive; see `ShaderRegistry`.
+    pub fn createWithStages(
+        device: &Arc<super::Device>,
+        name: String,
+        stages: ShaderStages,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let compiled = CompiledStages::compile(&name, stages, Some(device.enabledFeatures())).map_err(boxed)?;
 
-        let vertexReflect = reflectShader(&vertexBytes)?;
-        let fragmentReflect = reflectShader(&&fragmentBytes)?;
+        Self::fromCompiled(device, name, compiled)
+    }
+    /// Creates the shader from stages that were already compiled, e.g. by `createAll`.
+    fn fromCompiled(
+        device: &Arc<super::Device>,
+        name: String,
+        compiled: CompiledStages,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let spirvHash = compiled.hash();
 
-        let vertexModule = createShaderModule(&device, &vertexBytes)?;
-        let fragmentModule = createShaderModule(&device, &fragmentBytes)?;
+        device.shaderRegistry().getOrInsert(&name, spirvHash, || {
+            let modules = ShaderModules::fromCompiled(device, &name, compiled, 0)?;
 
-        let vertex = ShaderInfo {
-            module: vertexModule,
-            compileInfo: vertexInfo,
-            reflectionData: vertexReflect,
-        };
+            Ok(Arc::new(Shader {
+                name: name.to_owned(),
+                device: device.clone(),
+                spirvHash: AtomicU64::new(spirvHash),
+                modules: RwLock::new(Arc::new(modules)),
+            }))
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The current modules; they stay valid even if the shader is reloaded in the meantime.
+    pub fn modules(&self) -> Arc<ShaderModules> {
+        self.modules.read().unwrap().clone()
+    }
+    pub fn generation(&self) -> u64 {
+        self.modules().generation()
+    }
+    /// Recompiles every stage from its sources (re-reading files from disk) and reflects it again.
+    ///
+    /// On failure the current modules are kept. Pipelines created from this shader have to be rebuilt
+    /// to pick up the new modules, see `PipelineCache::rebuild`.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let current = self.modules();
 
-        let fragment = ShaderInfo {
-            module: fragmentModule,
-            compileInfo: fragmentInfo,
-            reflectionData: fragmentReflect,
-        };
-        // let pipelineStageCreateInfos = vec![
+        let compiled =
+            CompiledStages::compile(&self.name, current.reloadedSources()?, Some(self.device.enabledFeatures()))
+                .map_err(boxed)?;
+        let spirvHash = compiled.hash();
 
-        // vk::PipelineShaderStageCreateInfo::builder()
-        // .stage(vk::ShaderStageFlags::VERTEX)
-        // .module(vertexModule)
-        // .name(CString::new( vertexInfo.info.entryPoint ).unwrap().as_c_str())
-        // .build(),
+        let modules = ShaderModules::fromCompiled(&self.device, &self.name, compiled, current.generation() + 1)?;
 
-        // vk::PipelineShaderStageCreateInfo::builder()
-        // .stage(vk::ShaderStageFlags::FRAGMENT)
-        // .module(fragmentModule)
-        // .name(CString::new( fragmentInfo.info.entryPoint ).unwrap().as_c_str())
-        // .build()
+        *self.modules.write().unwrap() = Arc::new(modules);
 
-        //];
+        let oldHash = self.spirvHash.swap(spirvHash, Ordering::SeqCst);
+        self.device.shaderRegistry().rekey(&self.name, oldHash, spirvHash);
 
-        Ok(Arc::new(Shader {
-            name,
+        Ok(())
+    }
+    /// Files any stage was built from, without duplicates.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        let mut dependencies: Vec<_> = self
+            .modules()
+            .stages()
+            .into_iter()
+            .flat_map(|(_, info)| info.dependencies.iter().cloned())
+            .collect();
+
+        dependencies.sort();
+        dependencies.dedup();
+
+        dependencies
+    }
+    /// Hash of the current SPIR-V, which the shader is registered under; reloading updates it.
+    pub fn spirvHash(&self) -> u64 {
+        self.spirvHash.load(Ordering::SeqCst)
+    }
+    /// Checks that `T` matches the uniform or storage block bound as `name`, e.g. before uploading a `T` to it.
+    ///
+    /// The check is opt-in: nothing in binding or uploading calls it, so call it once per block type up front.
+    pub fn validateBlock<T: ShaderBlock>(&self, name: &str) -> Result<(), ShaderBlockError> {
+        let modules = self.modules();
+
+        modules
+            .stages()
+            .into_iter()
+            .find_map(|(_, info)| info.reflectionData.bindingNamed(name)?.block())
+            .ok_or_else(|| ShaderBlockError::UnknownBlock(name.to_owned(), self.name.to_owned()))?
+            .validate::<T>()
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        // The modules go away with the last reference to them, which pipelines may still hold.
+        self.device.shaderRegistry().forget(&self.name, *self.spirvHash.get_mut());
+    }
+}
+
+/// The compiled compute stage of a `ComputeShader`, replaced as a whole when the shader is reloaded.
+pub struct ComputeModule {
+    device: Arc<super::Device>,
+    generation: u64,
+    pub(crate) compute: ShaderInfo,
+}
+impl ComputeModule {
+    fn compile(
+        device: &Arc<super::Device>,
+        name: &str,
+        computeInfo: ShaderCompileInfo,
+    ) -> Result<CompiledStage, Box<dyn std::error::Error>> {
+        CompiledStage::compile(
+            &(name.to_owned() + "_compute"),
+            computeInfo,
+            ShaderStage::Compute,
+            Some(device.enabledFeatures()),
+        )
+        .map_err(boxed)
+    }
+    fn create(
+        device: &Arc<super::Device>,
+        compiled: CompiledStage,
+        generation: u64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let compute = ShaderInfo::create(device, compiled, &mut SharedModules::new())?;
+
+        Ok(Self {
             device: device.clone(),
-            vertex,
-            fragment,
-        }))
+            generation,
+            compute,
+        })
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+impl Drop for ComputeModule {
+    fn drop(&mut self) {
+        unsafe { self.device.raw().destroy_shader_module(self.compute.module, None) };
+    }
+}
+
+pub struct ComputeShader {
+    device: Arc<super::Device>,
+    pub(crate) name: String,
+    /// SPIR-V hash the shader is registered under in the device's `ShaderRegistry`.
+    spirvHash: AtomicU64,
+    module: RwLock<Arc<ComputeModule>>,
+}
+
+impl ComputeShader {
+    /// Compiles the shader, then hands out the already loaded one if one with the same name and SPIR-V is still
+    /// alive; see `ShaderRegistry`.
+    pub fn create(
+        device: &Arc<super::Device>,
+        name: String,
+        computeInfo: ShaderCompileInfo,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let compiled = ComputeModule::compile(device, &name, computeInfo)?;
+        let spirvHash = hashStages(std::iter::once(&compiled));
+
+        device.shaderRegistry().getOrInsertCompute(&name, spirvHash, || {
+            let module = ComputeModule::create(device, compiled, 0)?;
+
+            Ok(Arc::new(ComputeShader {
+                name: name.to_owned(),
+                device: device.clone(),
+                spirvHash: AtomicU64::new(spirvHash),
+                module: RwLock::new(Arc::new(module)),
+            }))
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The current module; it stays valid even if the shader is reloaded in the meantime.
+    pub fn module(&self) -> Arc<ComputeModule> {
+        self.module.read().unwrap().clone()
+    }
+    pub fn generation(&self) -> u64 {
+        self.module().generation()
+    }
+    /// Recompiles the shader from its source (re-reading the file from disk) and reflects it again.
+    ///
+    /// On failure the current module is kept. Compute pipelines aren't cached, so the ones created from this
+    /// shader have to be replaced by the caller, see `ComputePipeline::isOutdated` and `ComputePipeline::rebuild`.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let current = self.module();
+
+        let compiled = ComputeModule::compile(&self.device, &self.name, current.compute.compileInfo.reloaded()?)?;
+        let spirvHash = hashStages(std::iter::once(&compiled));
+
+        let module = ComputeModule::create(&self.device, compiled, current.generation() + 1)?;
+
+        *self.module.write().unwrap() = Arc::new(module);
+
+        let oldHash = self.spirvHash.swap(spirvHash, Ordering::SeqCst);
+        self.device.shaderRegistry().rekeyCompute(&self.name, oldHash, spirvHash);
+
+        Ok(())
+    }
+    /// Hash of the current SPIR-V, which the shader is registered under; reloading updates it.
+    pub fn spirvHash(&self) -> u64 {
+        self.spirvHash.load(Ordering::SeqCst)
+    }
+    /// Local workgroup size as declared by `layout(local_size_x = ...)`, defaults to 1 in every dimension.
+    pub fn workgroupSize(&self) -> [u32; 3] {
+        self.module()
+            .compute
+            .reflectionData
+            .workgroupSize()
+            .unwrap_or([1, 1, 1])
+    }
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        self.module().compute.dependencies().to_vec()
+    }
+    /// Checks that `T` matches the uniform or storage block bound as `name`, e.g. before uploading a `T` to it.
+    ///
+    /// The check is opt-in: nothing in binding or uploading calls it, so call it once per block type up front.
+    pub fn validateBlock<T: ShaderBlock>(&self, name: &str) -> Result<(), ShaderBlockError> {
+        self.module()
+            .compute
+            .reflectionData
+            .bindingNamed(name)
+            .and_then(|binding| binding.block())
+            .ok_or_else(|| ShaderBlockError::UnknownBlock(name.to_owned(), self.name.to_owned()))?
+            .validate::<T>()
+    }
+}
+
+impl Drop for ComputeShader {
+    fn drop(&mut self) {
+        self.device.shaderRegistry().forgetCompute(&self.name, *self.spirvHash.get_mut());
     }
 }
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{Shader, ShaderCreateError, ShaderDefines, ShaderStages};
+
+/// Compiles each variant of a shader once, keyed by the shader name and its defines.
+///
+/// Register the sources of a shader under a name, then ask for permutations of it; every permutation is
+/// compiled on first use and shared afterwards. Like `PipelineCache`, compilation happens outside the lock.
+pub struct ShaderPermutations {
+    device: Arc<crate::Device>,
+    sources: Mutex<HashMap<String, ShaderStages>>,
+    shaders: Mutex<HashMap<(String, ShaderDefines), Arc<Shader>>>,
+}
+impl ShaderPermutations {
+    pub fn new(device: &Arc<crate::Device>) -> Self {
+        Self {
+            device: device.clone(),
+            sources: Mutex::new(HashMap::new()),
+            shaders: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Registers (or replaces) the sources of `name`; permutations compiled from the old sources stay cached.
+    pub fn register(&self, name: &str, stages: ShaderStages) {
+        self.sources.lock().unwrap().insert(name.to_owned(), stages);
+    }
+    pub fn cached(&self, name: &str, defines: &ShaderDefines) -> Option<Arc<Shader>> {
+        self.shaders
+            .lock()
+            .unwrap()
+            .get(&(name.to_owned(), defines.clone()))
+            .cloned()
+    }
+    /// The shader registered as `name`, compiled with `defines` added to every stage.
+    pub fn get(&self, name: &str, defines: &ShaderDefines) -> Result<Arc<Shader>, Box<dyn std::error::Error>> {
+        if let Some(shader) = self.cached(name, defines) {
+            return Ok(shader);
+        }
+
+        let stages = self
+            .sources
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ShaderCreateError::UnknownShader(name.to_owned()))?;
+
+        let permutationName = if defines.isEmpty() {
+            name.to_owned()
+        } else {
+            format!("{}{}", name, defines)
+        };
+
+        let shader = Shader::createWithStages(&self.device, permutationName, stages.defines(defines))?;
+
+        // Another thread may have compiled the same permutation in the meantime, keep whichever got in first.
+        Ok(self
+            .shaders
+            .lock()
+            .unwrap()
+            .entry((name.to_owned(), defines.clone()))
+            .or_insert(shader)
+            .clone())
+    }
+    /// Every permutation compiled so far, e.g. to hand them to a `ShaderWatcher`.
+    pub fn shaders(&self) -> Vec<Arc<Shader>> {
+        self.shaders.lock().unwrap().values().cloned().collect()
+    }
+    pub fn len(&self) -> usize {
+        self.shaders.lock().unwrap().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shaders.lock().unwrap().is_empty()
+    }
+    pub fn clear(&self) {
+        self.shaders.lock().unwrap().clear();
+    }
+}
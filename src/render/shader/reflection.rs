@@ -1,8 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, convert::TryFrom};
 
-use spirv_reflect::{types::ReflectFormat, ShaderModule};
+use ash::vk;
+use spirv_reflect::{
+    types::{
+        ReflectBlockVariable, ReflectDecorationFlags, ReflectDescriptorBinding, ReflectDescriptorType,
+        ReflectDimension, ReflectImageFormat, ReflectInterfaceVariable,
+    },
+    ShaderModule,
+};
 
-use super::ShaderDataType;
+use super::{ShaderCreateError, ShaderDataType, ShaderDataTypeError, ShaderStage};
 
 #[derive(Debug)]
 pub struct ShaderVariable {
@@ -10,11 +17,415 @@ pub struct ShaderVariable {
     pub(crate) dataType: ShaderDataType,
     pub(crate) location: u32,
 }
+impl ShaderVariable {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn dataType(&self) -> &ShaderDataType {
+        &self.dataType
+    }
+    pub fn location(&self) -> u32 {
+        self.location
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMember {
+    pub(crate) name: String,
+    pub(crate) offset: u32,
+    pub(crate) size: u32,
+    pub(crate) arrayDimensions: Vec<u32>,
+    pub(crate) arrayStride: u32,
+    pub(crate) dataType: Option<ShaderDataType>,
+    pub(crate) members: Vec<BlockMember>,
+}
+impl BlockMember {
+    fn reflect(member: ReflectBlockVariable) -> Self {
+        let dataType = member
+            .type_description
+            .as_ref()
+            .and_then(|description| ShaderDataType::try_from(description).ok());
+
+        Self {
+            dataType,
+            name: member.name,
+            offset: member.absolute_offset,
+            size: member.size,
+            arrayDimensions: member.array.dims,
+            arrayStride: member.array.stride,
+            members: member.members.into_iter().map(Self::reflect).collect(),
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Offset from the start of the block, not of the enclosing struct.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+    /// Element counts of each array dimension, empty if the member isn't an array.
+    pub fn arrayDimensions(&self) -> &[u32] {
+        &self.arrayDimensions
+    }
+    pub fn arrayStride(&self) -> u32 {
+        self.arrayStride
+    }
+    /// `None` for types that can't be expressed as a `ShaderDataType`.
+    pub fn dataType(&self) -> Option<&ShaderDataType> {
+        self.dataType.as_ref()
+    }
+    /// Members of a struct-typed member.
+    pub fn members(&self) -> &[BlockMember] {
+        &self.members
+    }
+}
+
+/// naga wraps uniform and push constant structs in an unnamed single-member struct to decorate it as a `Block`,
+/// which puts the actual members one level down. Returns them along with the wrapped struct's name.
+fn unwrapBlock(mut members: Vec<ReflectBlockVariable>) -> (Option<String>, Vec<ReflectBlockVariable>) {
+    match members.as_slice() {
+        [member] if member.name.is_empty() && !member.members.is_empty() => {
+            let wrapped = members.pop().unwrap();
+            let typeName = wrapped
+                .type_description
+                .as_ref()
+                .map(|description| description.type_name.to_owned());
+
+            (typeName, wrapped.members)
+        }
+        _ => (None, members),
+    }
+}
+
+/// Layout of a uniform or storage buffer block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockLayout {
+    pub(crate) typeName: String,
+    pub(crate) size: u32,
+    pub(crate) members: Vec<BlockMember>,
+}
+impl BlockLayout {
+    pub fn typeName(&self) -> &str {
+        &self.typeName
+    }
+    /// Size up to the end of the last member; a trailing runtime array counts as empty.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+    pub fn members(&self) -> &[BlockMember] {
+        &self.members
+    }
+    pub fn member(&self, name: &str) -> Option<&BlockMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DescriptorType {
+    Sampler,
+    CombinedImageSampler,
+    SampledImage,
+    StorageImage,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    UniformBuffer,
+    StorageBuffer,
+    InputAttachment,
+    AccelerationStructure,
+}
+impl DescriptorType {
+    fn reflect(descriptorType: ReflectDescriptorType) -> Option<Self> {
+        Some(match descriptorType {
+            ReflectDescriptorType::Sampler => Self::Sampler,
+            ReflectDescriptorType::CombinedImageSampler => Self::CombinedImageSampler,
+            ReflectDescriptorType::SampledImage => Self::SampledImage,
+            ReflectDescriptorType::StorageImage => Self::StorageImage,
+            ReflectDescriptorType::UniformTexelBuffer => Self::UniformTexelBuffer,
+            ReflectDescriptorType::StorageTexelBuffer => Self::StorageTexelBuffer,
+            ReflectDescriptorType::UniformBuffer | ReflectDescriptorType::UniformBufferDynamic => {
+                Self::UniformBuffer
+            }
+            ReflectDescriptorType::StorageBuffer | ReflectDescriptorType::StorageBufferDynamic => {
+                Self::StorageBuffer
+            }
+            ReflectDescriptorType::InputAttachment => Self::InputAttachment,
+            ReflectDescriptorType::AccelerationStructureNV => Self::AccelerationStructure,
+            ReflectDescriptorType::Undefined => return None,
+        })
+    }
+}
+impl From<DescriptorType> for vk::DescriptorType {
+    fn from(descriptorType: DescriptorType) -> Self {
+        match descriptorType {
+            DescriptorType::Sampler => vk::DescriptorType::SAMPLER,
+            DescriptorType::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            DescriptorType::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+            DescriptorType::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+            DescriptorType::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+            DescriptorType::StorageTexelBuffer => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+            DescriptorType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+            DescriptorType::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+            DescriptorType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
+            DescriptorType::AccelerationStructure => vk::DescriptorType::ACCELERATION_STRUCTURE_NV,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageDimension {
+    Dim1D,
+    Dim2D,
+    Dim3D,
+    Cube,
+    Rect,
+    Buffer,
+    SubpassData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub(crate) dimension: ImageDimension,
+    pub(crate) arrayed: bool,
+    pub(crate) multisampled: bool,
+    pub(crate) depth: bool,
+    pub(crate) format: Option<vk::Format>,
+}
+impl ImageInfo {
+    fn reflect(binding: &ReflectDescriptorBinding) -> Option<Self> {
+        let image = &binding.image;
+
+        let dimension = match image.dim {
+            ReflectDimension::Type1d => ImageDimension::Dim1D,
+            ReflectDimension::Type2d => ImageDimension::Dim2D,
+            ReflectDimension::Type3d => ImageDimension::Dim3D,
+            ReflectDimension::Cube => ImageDimension::Cube,
+            ReflectDimension::Rect => ImageDimension::Rect,
+            ReflectDimension::Buffer => ImageDimension::Buffer,
+            ReflectDimension::SubPassData => ImageDimension::SubpassData,
+            ReflectDimension::Undefined => return None,
+        };
+
+        Some(Self {
+            dimension,
+            arrayed: image.arrayed != 0,
+            multisampled: image.ms != 0,
+            // 2 means "unknown", which is what most shaders declare.
+            depth: image.depth == 1,
+            format: imageFormat(image.image_format),
+        })
+    }
+    pub fn dimension(&self) -> ImageDimension {
+        self.dimension
+    }
+    pub fn arrayed(&self) -> bool {
+        self.arrayed
+    }
+    pub fn multisampled(&self) -> bool {
+        self.multisampled
+    }
+    /// Declared as a depth image, e.g. `sampler2DShadow`.
+    pub fn depth(&self) -> bool {
+        self.depth
+    }
+    /// Format given by a storage image's `layout(rgba8)` qualifier, `None` for sampled images.
+    pub fn format(&self) -> Option<vk::Format> {
+        self.format
+    }
+}
+
+fn imageFormat(format: ReflectImageFormat) -> Option<vk::Format> {
+    use ReflectImageFormat::*;
+
+    Some(match format {
+        Undefined => return None,
+        RGBA32_FLOAT => vk::Format::R32G32B32A32_SFLOAT,
+        RGBA16_FLOAT => vk::Format::R16G16B16A16_SFLOAT,
+        R32_FLOAT => vk::Format::R32_SFLOAT,
+        RGBA8 => vk::Format::R8G8B8A8_UNORM,
+        RGBA8_SNORM => vk::Format::R8G8B8A8_SNORM,
+        RG32_FLOAT => vk::Format::R32G32_SFLOAT,
+        RG16_FLOAT => vk::Format::R16G16_SFLOAT,
+        R11G11B10_FLOAT => vk::Format::B10G11R11_UFLOAT_PACK32,
+        R16_FLOAT => vk::Format::R16_SFLOAT,
+        RGBA16 => vk::Format::R16G16B16A16_UNORM,
+        RGB10A2 => vk::Format::A2B10G10R10_UNORM_PACK32,
+        RG16 => vk::Format::R16G16_UNORM,
+        RG8 => vk::Format::R8G8_UNORM,
+        R16 => vk::Format::R16_UNORM,
+        R8 => vk::Format::R8_UNORM,
+        RGBA16_SNORM => vk::Format::R16G16B16A16_SNORM,
+        RG16_SNORM => vk::Format::R16G16_SNORM,
+        RG8_SNORM => vk::Format::R8G8_SNORM,
+        R16_SNORM => vk::Format::R16_SNORM,
+        R8_SNORM => vk::Format::R8_SNORM,
+        RGBA32_INT => vk::Format::R32G32B32A32_SINT,
+        RGBA16_INT => vk::Format::R16G16B16A16_SINT,
+        RGBA8_INT => vk::Format::R8G8B8A8_SINT,
+        R32_INT => vk::Format::R32_SINT,
+        RG32_INT => vk::Format::R32G32_SINT,
+        RG16_INT => vk::Format::R16G16_SINT,
+        RG8_INT => vk::Format::R8G8_SINT,
+        R16_INT => vk::Format::R16_SINT,
+        R8_INT => vk::Format::R8_SINT,
+        RGBA32_UINT => vk::Format::R32G32B32A32_UINT,
+        RGBA16_UINT => vk::Format::R16G16B16A16_UINT,
+        RGBA8_UINT => vk::Format::R8G8B8A8_UINT,
+        R32_UINT => vk::Format::R32_UINT,
+        RGB10A2_UINT => vk::Format::A2B10G10R10_UINT_PACK32,
+        RG32_UINT => vk::Format::R32G32_UINT,
+        RG16_UINT => vk::Format::R16G16_UINT,
+        RG8_UINT => vk::Format::R8G8_UINT,
+        R16_UINT => vk::Format::R16_UINT,
+        R8_UINT => vk::Format::R8_UINT,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptorBinding {
+    pub(crate) name: String,
+    pub(crate) set: u32,
+    pub(crate) binding: u32,
+    pub(crate) descriptorType: DescriptorType,
+    pub(crate) count: u32,
+    pub(crate) image: Option<ImageInfo>,
+    pub(crate) block: Option<BlockLayout>,
+}
+impl DescriptorBinding {
+    fn reflect(binding: ReflectDescriptorBinding) -> Option<Self> {
+        let descriptorType = DescriptorType::reflect(binding.descriptor_type)?;
+
+        // spirv-reflect leaves the image description zeroed for other descriptors, which reads as a 1D image.
+        let image = match descriptorType {
+            DescriptorType::SampledImage
+            | DescriptorType::CombinedImageSampler
+            | DescriptorType::StorageImage
+            | DescriptorType::UniformTexelBuffer
+            | DescriptorType::StorageTexelBuffer
+            | DescriptorType::InputAttachment => ImageInfo::reflect(&binding),
+            _ => None,
+        };
+
+        let typeName = binding
+            .type_description
+            .as_ref()
+            .map(|description| description.type_name.to_owned())
+            .unwrap_or_default();
+
+        let (wrappedTypeName, members) = unwrapBlock(binding.block.members);
+        let typeName = wrappedTypeName.unwrap_or(typeName);
+
+        let block = match descriptorType {
+            DescriptorType::UniformBuffer | DescriptorType::StorageBuffer => Some(BlockLayout {
+                typeName: typeName.to_owned(),
+                size: binding.block.size,
+                members: members.into_iter().map(BlockMember::reflect).collect(),
+            }),
+            _ => None,
+        };
+
+        // Blocks declared without an instance name are only known by their type name.
+        let name = if binding.name.is_empty() { typeName } else { binding.name };
+
+        Some(Self {
+            name,
+            set: binding.set,
+            binding: binding.binding,
+            descriptorType,
+            count: binding.count,
+            image,
+            block,
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn set(&self) -> u32 {
+        self.set
+    }
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+    pub fn descriptorType(&self) -> DescriptorType {
+        self.descriptorType
+    }
+    /// Number of descriptors, greater than 1 for arrays of resources.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+    pub fn image(&self) -> Option<&ImageInfo> {
+        self.image.as_ref()
+    }
+    /// Member layout of uniform and storage buffers.
+    pub fn block(&self) -> Option<&BlockLayout> {
+        self.block.as_ref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PushConstantBlock {
+    pub(crate) name: String,
+    pub(crate) members: Vec<BlockMember>,
+}
+impl PushConstantBlock {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn members(&self) -> &[BlockMember] {
+        &self.members
+    }
+    /// Byte range (offset, size) covered by the members of this block.
+    pub fn range(&self) -> (u32, u32) {
+        let start = self.members.iter().map(|m| m.offset).min().unwrap_or(0);
+        let end = self
+            .members
+            .iter()
+            .map(|m| m.offset + m.size)
+            .max()
+            .unwrap_or(0);
+
+        (start, end - start)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecializationType {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpecializationConstant {
+    pub(crate) name: String,
+    pub(crate) id: u32,
+    pub(crate) constantType: SpecializationType,
+}
+impl SpecializationConstant {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn constantType(&self) -> SpecializationType {
+        self.constantType
+    }
+}
 
 pub struct ReflectionData {
     inputs: HashMap<String, ShaderVariable>,
     outputs: HashMap<String, ShaderVariable>,
-    samplers: HashSet<String>,
+    bindings: Vec<DescriptorBinding>,
+    pushConstants: Option<PushConstantBlock>,
+    specializationConstants: Vec<SpecializationConstant>,
+    workgroupSize: Option<[u32; 3]>,
+    workgroupSizeSpecIds: [Option<u32>; 3],
+    outputVertices: Option<u32>,
 }
 impl ReflectionData {
     pub fn inputs(&self) -> &HashMap<String, ShaderVariable> {
@@ -23,65 +434,497 @@ impl ReflectionData {
     pub fn outputs(&self) -> &HashMap<String, ShaderVariable> {
         &self.outputs
     }
-    pub fn samplers(&self) -> &HashSet<String> {
-        &self.samplers
+    /// Every descriptor binding, ordered by set and binding.
+    pub fn bindings(&self) -> &[DescriptorBinding] {
+        &self.bindings
+    }
+    pub fn bindingNamed(&self, name: &str) -> Option<&DescriptorBinding> {
+        self.bindings.iter().find(|binding| binding.name == name)
+    }
+    pub fn binding(&self, set: u32, binding: u32) -> Option<&DescriptorBinding> {
+        self.bindings
+            .iter()
+            .find(|candidate| candidate.set == set && candidate.binding == binding)
+    }
+    pub fn pushConstants(&self) -> Option<&PushConstantBlock> {
+        self.pushConstants.as_ref()
+    }
+    pub fn specializationConstants(&self) -> &[SpecializationConstant] {
+        &self.specializationConstants
+    }
+    /// Local workgroup size declared by a compute shader, `None` for other stages.
+    pub fn workgroupSize(&self) -> Option<[u32; 3]> {
+        self.workgroupSize
+    }
+    /// Specialization constant ids backing each workgroup size dimension (`local_size_x_id` etc.), if any.
+    pub fn workgroupSizeSpecIds(&self) -> [Option<u32>; 3] {
+        self.workgroupSizeSpecIds
+    }
+    /// `layout(vertices = N)` of a tessellation control shader, or `max_vertices` of a geometry shader.
+    pub fn outputVertices(&self) -> Option<u32> {
+        self.outputVertices
     }
 }
 
-pub fn reflectShader(spirvData: &[u8]) -> Result<ReflectionData, Box<dyn std::error::Error>> {
-    let module = ShaderModule::load_u8_data(spirvData)?;
-    let inputVars = module.enumerate_input_variables(None)?;
-    let mut inputs = HashMap::new();
-
-    for var in inputVars {
-        let layout = ShaderVariable {
-            name: var.name.to_owned(),
-            dataType: var.format.into(),
-            location: var.location,
-        };
+const SPIRV_HEADER_WORDS: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_SPEC_CONSTANT_COMPOSITE: u32 = 51;
+const OP_DECORATE: u32 = 71;
 
-        log::info!("{:?}", var.format);
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+const EXECUTION_MODE_OUTPUT_VERTICES: u32 = 26;
+
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BUILT_IN: u32 = 11;
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+
+fn spirvWords(spirvData: &[u8]) -> Vec<u32> {
+    spirvData
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect()
+}
+/// Walks the instruction stream, yielding (opcode, operands) pairs.
+fn spirvInstructions(words: &[u32]) -> impl Iterator<Item = (u32, &[u32])> {
+    let mut cursor = SPIRV_HEADER_WORDS;
 
-        inputs.insert(var.name, layout);
+    std::iter::from_fn(move || {
+        let first = *words.get(cursor)?;
+        let wordCount = (first >> 16) as usize;
+
+        if wordCount == 0 || cursor + wordCount > words.len() {
+            return None;
+        }
+
+        let operands = &words[cursor + 1..cursor + wordCount];
+        cursor += wordCount;
+
+        Some((first & 0xffff, operands))
+    })
+}
+fn spirvString(operands: &[u32]) -> String {
+    let bytes: Vec<u8> = operands
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|&byte| byte != 0)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+fn reflectSpecializationConstants(words: &[u32]) -> (Vec<SpecializationConstant>, [Option<u32>; 3]) {
+    let mut names = HashMap::new();
+    let mut specIds = HashMap::new();
+    let mut types = HashMap::new();
+    let mut constants = Vec::new();
+    let mut workgroupSizeId = None;
+    let mut composites = HashMap::new();
+
+    for (opcode, operands) in spirvInstructions(words) {
+        match (opcode, operands) {
+            (OP_NAME, &[target, ref name @ ..]) => {
+                names.insert(target, spirvString(name));
+            }
+            (OP_DECORATE, &[target, DECORATION_SPEC_ID, id]) => {
+                specIds.insert(target, id);
+            }
+            (OP_DECORATE, &[target, DECORATION_BUILT_IN, BUILT_IN_WORKGROUP_SIZE]) => {
+                workgroupSizeId = Some(target);
+            }
+            (OP_TYPE_BOOL, &[result]) => {
+                types.insert(result, SpecializationType::Bool);
+            }
+            (OP_TYPE_INT, &[result, 32, signedness]) => {
+                let intType = if signedness == 0 {
+                    SpecializationType::UInt
+                } else {
+                    SpecializationType::Int
+                };
+                types.insert(result, intType);
+            }
+            (OP_TYPE_FLOAT, &[result, 32]) => {
+                types.insert(result, SpecializationType::Float);
+            }
+            (OP_TYPE_FLOAT, &[result, 64]) => {
+                types.insert(result, SpecializationType::Double);
+            }
+            (OP_SPEC_CONSTANT_TRUE, &[resultType, result])
+            | (OP_SPEC_CONSTANT_FALSE, &[resultType, result])
+            | (OP_SPEC_CONSTANT, &[resultType, result, ..]) => {
+                constants.push((resultType, result));
+            }
+            (OP_SPEC_CONSTANT_COMPOSITE, &[_resultType, result, ref constituents @ ..]) => {
+                composites.insert(result, constituents.to_vec());
+            }
+            _ => {}
+        }
     }
 
-    let outputVars = module.enumerate_input_variables(None)?;
+    let workgroupSizeSpecIds = match workgroupSizeId.and_then(|id| composites.get(&id)) {
+        Some(constituents) if constituents.len() == 3 => [
+            specIds.get(&constituents[0]).copied(),
+            specIds.get(&constituents[1]).copied(),
+            specIds.get(&constituents[2]).copied(),
+        ],
+        _ => [None; 3],
+    };
 
-    let mut outputs = HashMap::new();
+    let constants = constants
+        .into_iter()
+        .filter_map(|(resultType, result)| {
+            let id = *specIds.get(&result)?;
 
-    for var in outputVars {
-        let layout = ShaderVariable {
-            name: var.name.to_owned(),
-            dataType: var.format.into(),
-            location: var.location,
-        };
+            let constantType = match types.get(&resultType) {
+                Some(&constantType) => constantType,
+                None => {
+                    log::warn!("Specialization constant {} has an unsupported type, ignoring", id);
+                    return None;
+                }
+            };
+
+            Some(SpecializationConstant {
+                name: names.get(&result).cloned().unwrap_or_default(),
+                id,
+                constantType,
+            })
+        })
+        .collect();
+
+    (constants, workgroupSizeSpecIds)
+}
+/// `OpExecutionMode` operands (after the entry point) of `entryPoint`, or of any entry point if `None`.
+fn executionModes(words: &[u32], entryPoint: Option<u32>) -> impl Iterator<Item = &[u32]> {
+    spirvInstructions(words).filter_map(move |(opcode, operands)| match operands {
+        &[target, ref mode @ ..] if opcode == OP_EXECUTION_MODE && entryPoint.unwrap_or(target) == target => {
+            Some(mode)
+        }
+        _ => None,
+    })
+}
+fn reflectWorkgroupSize(words: &[u32], entryPoint: Option<u32>) -> Option<[u32; 3]> {
+    executionModes(words, entryPoint).find_map(|mode| match mode {
+        &[EXECUTION_MODE_LOCAL_SIZE, x, y, z] => Some([x, y, z]),
+        _ => None,
+    })
+}
+fn reflectOutputVertices(words: &[u32], entryPoint: Option<u32>) -> Option<u32> {
+    executionModes(words, entryPoint).find_map(|mode| match mode {
+        &[EXECUTION_MODE_OUTPUT_VERTICES, count] => Some(count),
+        _ => None,
+    })
+}
 
-        outputs.insert(var.name, layout);
+/// An entry point declared in a SPIR-V module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub(crate) name: String,
+    pub(crate) stage: ShaderStage,
+    id: u32,
+}
+impl EntryPoint {
+    pub fn name(&self) -> &str {
+        &self.name
     }
+    pub fn stage(&self) -> ShaderStage {
+        self.stage
+    }
+}
 
-    //Remove gl defaults
+/// Every entry point of a graphics or compute stage; kernels, mesh and ray tracing stages are skipped.
+fn reflectEntryPoints(words: &[u32]) -> Vec<EntryPoint> {
+    spirvInstructions(words)
+        .filter_map(|(opcode, operands)| match operands {
+            &[model, id, ref name @ ..] if opcode == OP_ENTRY_POINT => {
+                let stage = match model {
+                    0 => ShaderStage::Vertex,
+                    1 => ShaderStage::TessellationControl,
+                    2 => ShaderStage::TessellationEvaluation,
+                    3 => ShaderStage::Geometry,
+                    4 => ShaderStage::Fragment,
+                    5 => ShaderStage::Compute,
+                    _ => return None,
+                };
 
-    let bindings = module.enumerate_descriptor_bindings(None)?;
-    let mut samplers = HashSet::new();
+                Some(EntryPoint {
+                    name: spirvString(name),
+                    stage,
+                    id,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+pub fn entryPoints(spirvData: &[u8]) -> Vec<EntryPoint> {
+    reflectEntryPoints(&spirvWords(spirvData))
+}
+/// Checks that the module declares `entryPoint` for `stage`, so a wrong name fails here rather than at
+/// pipeline creation.
+pub fn validateEntryPoint(
+    shaderName: &str,
+    spirvData: &[u8],
+    entryPoint: &str,
+    stage: ShaderStage,
+) -> Result<(), ShaderCreateError> {
+    let entryPoints = entryPoints(spirvData);
 
-    for binding in bindings {
-        samplers.insert(binding.name);
+    if entryPoints
+        .iter()
+        .any(|candidate| candidate.name == entryPoint && candidate.stage == stage)
+    {
+        return Ok(());
     }
 
+    let available: Vec<_> = entryPoints
+        .iter()
+        .map(|candidate| format!("{} ({:?})", candidate.name, candidate.stage))
+        .collect();
+
+    Err(ShaderCreateError::MissingEntryPoint(
+        shaderName.to_owned(),
+        entryPoint.to_owned(),
+        stage,
+        if available.is_empty() {
+            "none".to_owned()
+        } else {
+            available.join(", ")
+        },
+    ))
+}
+
+/// Inputs or outputs by name, without built-ins such as `gl_Position` or the `gl_PerVertex` block.
+fn interfaceVariables(
+    variables: Vec<ReflectInterfaceVariable>,
+) -> Result<HashMap<String, ShaderVariable>, ShaderDataTypeError> {
+    variables
+        .into_iter()
+        .filter(|var| {
+            !var.decoration_flags.contains(ReflectDecorationFlags::BUILT_IN) && !var.name.starts_with("gl_")
+        })
+        .map(|var| {
+            // The format only covers 32-bit scalars and vectors, the type description has the full picture.
+            let dataType = match &var.type_description {
+                Some(description) => ShaderDataType::try_from(description)?,
+                None => ShaderDataType::try_from(var.format)?,
+            };
+
+            let layout = ShaderVariable {
+                name: var.name.to_owned(),
+                dataType,
+                location: var.location,
+            };
+
+            Ok((var.name, layout))
+        })
+        .collect()
+}
+
+/// Reflects the first entry point of a module.
+pub fn reflectShader(spirvData: &[u8]) -> Result<ReflectionData, Box<dyn std::error::Error>> {
+    reflect(spirvData, None)
+}
+/// Reflects a single entry point of a module that may contain several, e.g. a vertex and a fragment `main`.
+pub fn reflectEntryPoint(
+    spirvData: &[u8],
+    entryPoint: &str,
+    stage: ShaderStage,
+) -> Result<ReflectionData, Box<dyn std::error::Error>> {
+    reflect(spirvData, Some((entryPoint, stage)))
+}
+fn reflect(
+    spirvData: &[u8],
+    entryPoint: Option<(&str, ShaderStage)>,
+) -> Result<ReflectionData, Box<dyn std::error::Error>> {
+    let words = spirvWords(spirvData);
+
+    let entryPointId = match entryPoint {
+        Some((name, stage)) => Some(
+            reflectEntryPoints(&words)
+                .into_iter()
+                .find(|candidate| candidate.name == name && candidate.stage == stage)
+                .map(|candidate| candidate.id)
+                .ok_or_else(|| ShaderCreateError::UnknownEntryPoint(name.to_owned(), stage))?,
+        ),
+        None => None,
+    };
+
+    let module = ShaderModule::load_u8_data(spirvData)?;
+
+    // spirv-reflect looks entry points up by name alone, so a single one is picked out by its id instead, with the
+    // interface variables, descriptors and push constants its OpEntryPoint and function bodies use.
+    let (inputs, outputs, bindings, pushConstantBlocks) = match entryPointId {
+        Some(id) => {
+            let entry = module
+                .enumerate_entry_points()?
+                .into_iter()
+                .find(|entry| entry.id == id)
+                .ok_or("spirv-reflect didn't find an entry point declared by the module")?;
+
+            let pushConstantBlocks = module
+                .enumerate_push_constant_blocks(None)?
+                .into_iter()
+                .filter(|block| entry.used_push_constants.contains(&block.spirv_id))
+                .collect();
+
+            let bindings = entry.descriptor_sets.into_iter().flat_map(|set| set.bindings).collect();
+
+            (entry.input_variables, entry.output_variables, bindings, pushConstantBlocks)
+        }
+        None => (
+            module.enumerate_input_variables(None)?,
+            module.enumerate_output_variables(None)?,
+            module.enumerate_descriptor_bindings(None)?,
+            module.enumerate_push_constant_blocks(None)?,
+        ),
+    };
+
+    let inputs = interfaceVariables(inputs)?;
+
+    let outputs = interfaceVariables(outputs)?;
+
+    let mut bindings: Vec<_> = bindings.into_iter().filter_map(DescriptorBinding::reflect).collect();
+
+    bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
     for input in &inputs {
         log::info!("{:?}", input);
     }
 
-    for sampler in &samplers {
-        log::info!("{:?}", sampler);
+    for binding in &bindings {
+        log::info!("{:?}", binding);
     }
 
     for output in &outputs {
         log::info!("{:?}", output);
     }
+    let pushConstants = pushConstantBlocks
+        .into_iter()
+        .next()
+        .map(|block| PushConstantBlock {
+            name: block.name,
+            members: unwrapBlock(block.members).1.into_iter().map(BlockMember::reflect).collect(),
+        });
+
+    if let Some(pushConstants) = &pushConstants {
+        log::info!("{:?}", pushConstants);
+    }
+
+    let workgroupSize = reflectWorkgroupSize(&words, entryPointId);
+
+    let outputVertices = reflectOutputVertices(&words, entryPointId);
+
+    let (specializationConstants, workgroupSizeSpecIds) = reflectSpecializationConstants(&words);
+
+    for constant in &specializationConstants {
+        log::info!("{:?}", constant);
+    }
+
     Ok(ReflectionData {
         inputs,
         outputs,
-        samplers,
+        bindings,
+        pushConstants,
+        specializationConstants,
+        workgroupSize,
+        workgroupSizeSpecIds,
+        outputVertices,
     })
 }
+
+#[cfg(all(test, feature = "wgsl"))]
+mod tests {
+    use naga::{back::spv, front::wgsl, valid};
+
+    use super::*;
+
+    const STAGES: &str = "struct Varyings {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> tint: vec4<f32>;
+@group(0) @binding(1) var<uniform> fog: vec4<f32>;
+
+@vertex
+fn mainV(@location(0) position: vec2<f32>) -> Varyings {
+    var output: Varyings;
+    output.position = vec4<f32>(position, 0.0, 1.0);
+    output.color = tint;
+    return output;
+}
+
+@fragment
+fn mainF(input: Varyings) -> @location(0) vec4<f32> {
+    return input.color * fog;
+}
+";
+
+    /// A module with a vertex and a fragment entry point that are both called `main`.
+    fn sameNamedStages() -> Vec<u8> {
+        let module = wgsl::parse_str(STAGES).unwrap();
+        let info = valid::Validator::new(valid::ValidationFlags::all(), valid::Capabilities::all())
+            .validate(&module)
+            .unwrap();
+        let options = spv::Options {
+            flags: spv::WriterFlags::DEBUG | spv::WriterFlags::LABEL_VARYINGS,
+            ..spv::Options::default()
+        };
+
+        let mut bytes: Vec<u8> = spv::write_vec(&module, &info, &options, None)
+            .unwrap()
+            .iter()
+            .flat_map(|word| word.to_le_bytes().to_vec())
+            .collect();
+
+        // Both names take two words, so they can be swapped for `main` in place.
+        for name in [b"mainV", b"mainF"].iter() {
+            let pattern = [&name[..], &[0; 3]].concat();
+            let mut start = 0;
+
+            while let Some(offset) = bytes[start..].windows(8).position(|window| window == &pattern[..]) {
+                bytes[start + offset + 4] = 0;
+                start += offset + 8;
+            }
+        }
+
+        bytes
+    }
+
+    fn names<'a>(variables: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+        let mut names: Vec<_> = variables.map(String::as_str).collect();
+        names.sort_unstable();
+
+        names
+    }
+
+    #[test]
+    fn reflectsSameNamedEntryPointsPerStage() {
+        let spirv = sameNamedStages();
+        let stages: Vec<_> = entryPoints(&spirv).into_iter().map(|entry| (entry.name, entry.stage)).collect();
+        assert_eq!(
+            stages,
+            [("main".to_owned(), ShaderStage::Vertex), ("main".to_owned(), ShaderStage::Fragment)]
+        );
+
+        let vertex = reflectEntryPoint(&spirv, "main", ShaderStage::Vertex).unwrap();
+        assert_eq!(names(vertex.inputs().keys()), ["position"]);
+        assert_eq!(names(vertex.outputs().keys()), ["color"]);
+        let bindings: Vec<_> = vertex.bindings().iter().map(|binding| binding.name()).collect();
+        assert_eq!(bindings, ["tint"]);
+
+        let fragment = reflectEntryPoint(&spirv, "main", ShaderStage::Fragment).unwrap();
+        assert_eq!(names(fragment.inputs().keys()), ["color"]);
+        let bindings: Vec<_> = fragment.bindings().iter().map(|binding| binding.name()).collect();
+        assert_eq!(bindings, ["fog"]);
+
+        assert!(reflectEntryPoint(&spirv, "main", ShaderStage::Compute).is_err());
+    }
+}
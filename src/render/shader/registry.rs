@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, Weak},
+};
+
+use super::{reflection::DescriptorType, ComputeShader, Shader, ShaderInfo, ShaderStage};
+
+/// What a loaded shader looks like from the outside, for debug overlays and logs.
+#[derive(Debug, Clone)]
+pub struct ShaderSummary {
+    pub name: String,
+    pub spirvHash: u64,
+    pub generation: u64,
+    pub stages: Vec<ShaderStage>,
+    /// Set, binding, name and type of every descriptor used by any stage.
+    pub bindings: Vec<(u32, u32, String, DescriptorType)>,
+    /// Size of the largest push constant block of any stage.
+    pub pushConstantSize: Option<u32>,
+    pub dependencies: Vec<PathBuf>,
+}
+impl ShaderSummary {
+    fn of(shader: &Shader) -> Self {
+        let modules = shader.modules();
+        let stages = modules.stages();
+
+        Self::ofStages(
+            shader.name(),
+            shader.spirvHash(),
+            modules.generation(),
+            stages.iter().map(|(_, info)| *info).collect(),
+            shader.dependencies(),
+        )
+    }
+    fn ofCompute(shader: &ComputeShader) -> Self {
+        let module = shader.module();
+
+        Self::ofStages(
+            shader.name(),
+            shader.spirvHash(),
+            module.generation(),
+            vec![&module.compute],
+            shader.dependencies(),
+        )
+    }
+    fn ofStages(
+        name: &str,
+        spirvHash: u64,
+        generation: u64,
+        stages: Vec<&ShaderInfo>,
+        dependencies: Vec<PathBuf>,
+    ) -> Self {
+        let mut bindings: Vec<_> = stages
+            .iter()
+            .flat_map(|info| info.reflectionData.bindings())
+            .map(|binding| {
+                (
+                    binding.set(),
+                    binding.binding(),
+                    binding.name().to_owned(),
+                    binding.descriptorType(),
+                )
+            })
+            .collect();
+
+        bindings.sort_by_key(|&(set, binding, _, _)| (set, binding));
+        bindings.dedup_by_key(|&mut (set, binding, _, _)| (set, binding));
+
+        let pushConstantSize = stages
+            .iter()
+            .filter_map(|info| info.reflectionData.pushConstants())
+            .map(|block| {
+                let (offset, size) = block.range();
+
+                offset + size
+            })
+            .max();
+
+        Self {
+            name: name.to_owned(),
+            spirvHash,
+            generation,
+            stages: stages.iter().map(|info| info.stage).collect(),
+            bindings,
+            pushConstantSize,
+            dependencies,
+        }
+    }
+}
+
+/// Weak references to live shaders of one kind, keyed by name and SPIR-V hash.
+struct ShaderMap<S> {
+    shaders: Mutex<HashMap<(String, u64), Weak<S>>>,
+}
+impl<S> ShaderMap<S> {
+    fn new() -> Self {
+        Self {
+            shaders: Mutex::new(HashMap::new()),
+        }
+    }
+    fn get(&self, name: &str, spirvHash: u64) -> Option<Arc<S>> {
+        self.shaders
+            .lock()
+            .unwrap()
+            .get(&(name.to_owned(), spirvHash))
+            .and_then(Weak::upgrade)
+    }
+    fn getOrInsert<F>(&self, name: &str, spirvHash: u64, create: F) -> Result<Arc<S>, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Result<Arc<S>, Box<dyn std::error::Error>>,
+    {
+        if let Some(shader) = self.get(name, spirvHash) {
+            return Ok(shader);
+        }
+
+        let shader = create()?;
+
+        let mut shaders = self.shaders.lock().unwrap();
+        let entry = shaders.entry((name.to_owned(), spirvHash)).or_default();
+
+        match entry.upgrade() {
+            Some(existing) => Ok(existing),
+            None => {
+                *entry = Arc::downgrade(&shader);
+
+                Ok(shader)
+            }
+        }
+    }
+    /// Moves the entry of a reloaded shader to the hash of its new SPIR-V. If a live shader is registered there
+    /// already, that one stays and the reloaded shader is no longer handed out.
+    fn rekey(&self, name: &str, oldHash: u64, newHash: u64) {
+        let mut shaders = self.shaders.lock().unwrap();
+
+        let shader = match shaders.remove(&(name.to_owned(), oldHash)) {
+            Some(shader) => shader,
+            None => return,
+        };
+
+        let entry = shaders.entry((name.to_owned(), newHash)).or_default();
+
+        if entry.strong_count() == 0 {
+            *entry = shader;
+        }
+    }
+    fn forget(&self, name: &str, spirvHash: u64) {
+        let mut shaders = self.shaders.lock().unwrap();
+        let key = (name.to_owned(), spirvHash);
+
+        if matches!(shaders.get(&key), Some(shader) if shader.strong_count() == 0) {
+            shaders.remove(&key);
+        }
+    }
+    fn live(&self) -> Vec<Arc<S>> {
+        self.shaders
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+/// Every graphics and compute shader loaded on a device, keyed by name and SPIR-V hash.
+///
+/// Creating a shader whose name and compiled SPIR-V match one that's still alive hands out that shader
+/// instead of creating new modules. Only weak references are kept, so the modules are destroyed as soon as
+/// the last `Arc<Shader>` (and the last pipeline using them) goes away.
+pub struct ShaderRegistry {
+    shaders: ShaderMap<Shader>,
+    computeShaders: ShaderMap<ComputeShader>,
+}
+impl ShaderRegistry {
+    pub(crate) fn new() -> Self {
+        Self {
+            shaders: ShaderMap::new(),
+            computeShaders: ShaderMap::new(),
+        }
+    }
+    pub fn get(&self, name: &str, spirvHash: u64) -> Option<Arc<Shader>> {
+        self.shaders.get(name, spirvHash)
+    }
+    pub fn getCompute(&self, name: &str, spirvHash: u64) -> Option<Arc<ComputeShader>> {
+        self.computeShaders.get(name, spirvHash)
+    }
+    /// The live shader registered under `name` and `spirvHash`, or the one `create` makes.
+    ///
+    /// `create` runs outside the lock; if another thread registered the same shader in the meantime, that one
+    /// wins and the new one is dropped.
+    pub(crate) fn getOrInsert<F>(
+        &self,
+        name: &str,
+        spirvHash: u64,
+        create: F,
+    ) -> Result<Arc<Shader>, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Result<Arc<Shader>, Box<dyn std::error::Error>>,
+    {
+        self.shaders.getOrInsert(name, spirvHash, create)
+    }
+    /// `getOrInsert` for compute shaders.
+    pub(crate) fn getOrInsertCompute<F>(
+        &self,
+        name: &str,
+        spirvHash: u64,
+        create: F,
+    ) -> Result<Arc<ComputeShader>, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Result<Arc<ComputeShader>, Box<dyn std::error::Error>>,
+    {
+        self.computeShaders.getOrInsert(name, spirvHash, create)
+    }
+    /// Re-registers a reloaded shader under the hash of its new SPIR-V, so that creating it from its original
+    /// sources doesn't hand out the reloaded modules.
+    pub(crate) fn rekey(&self, name: &str, oldHash: u64, newHash: u64) {
+        self.shaders.rekey(name, oldHash, newHash);
+    }
+    pub(crate) fn rekeyCompute(&self, name: &str, oldHash: u64, newHash: u64) {
+        self.computeShaders.rekey(name, oldHash, newHash);
+    }
+    /// Drops the entry of a shader that is going away, unless it was replaced by a live one already.
+    pub(crate) fn forget(&self, name: &str, spirvHash: u64) {
+        self.shaders.forget(name, spirvHash);
+    }
+    pub(crate) fn forgetCompute(&self, name: &str, spirvHash: u64) {
+        self.computeShaders.forget(name, spirvHash);
+    }
+    /// Every graphics shader that's still alive.
+    pub fn shaders(&self) -> Vec<Arc<Shader>> {
+        self.shaders.live()
+    }
+    /// Every compute shader that's still alive.
+    pub fn computeShaders(&self) -> Vec<Arc<ComputeShader>> {
+        self.computeShaders.live()
+    }
+    pub fn summaries(&self) -> Vec<ShaderSummary> {
+        let mut summaries: Vec<_> = self
+            .shaders()
+            .iter()
+            .map(|shader| ShaderSummary::of(shader))
+            .chain(self.computeShaders().iter().map(|shader| ShaderSummary::ofCompute(shader)))
+            .collect();
+
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        summaries
+    }
+    pub fn len(&self) -> usize {
+        self.shaders().len() + self.computeShaders().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::render::graph::PipelineCache;
+
+use super::{ComputeShader, Shader};
+
+/// What the watcher needs from graphics and compute shaders alike.
+trait Reloadable {
+    fn name(&self) -> &str;
+    fn dependencies(&self) -> Vec<PathBuf>;
+    fn reload(&self) -> Result<(), Box<dyn std::error::Error>>;
+}
+impl Reloadable for Shader {
+    fn name(&self) -> &str {
+        Shader::name(self)
+    }
+    fn dependencies(&self) -> Vec<PathBuf> {
+        Shader::dependencies(self)
+    }
+    fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Shader::reload(self)
+    }
+}
+impl Reloadable for ComputeShader {
+    fn name(&self) -> &str {
+        ComputeShader::name(self)
+    }
+    fn dependencies(&self) -> Vec<PathBuf> {
+        ComputeShader::dependencies(self)
+    }
+    fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        ComputeShader::reload(self)
+    }
+}
+
+struct WatchedShader<S> {
+    shader: Weak<S>,
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+impl<S: Reloadable> WatchedShader<S> {
+    fn new(shader: &Arc<S>) -> Self {
+        Self {
+            shader: Arc::downgrade(shader),
+            modified: modificationTimes(&**shader),
+        }
+    }
+    /// Reloads the shader if any of its files changed, returning it if that succeeded.
+    fn poll(&mut self) -> Option<Arc<S>> {
+        let shader = self.shader.upgrade()?;
+
+        let modified = modificationTimes(&*shader);
+
+        if modified == self.modified {
+            return None;
+        }
+
+        // Remember the new timestamps even if compilation fails, so the error is only reported once per save.
+        self.modified = modified;
+
+        match shader.reload() {
+            Ok(()) => {
+                log::info!("Reloaded shader {:?}", shader.name());
+
+                // Includes may have been added or removed.
+                self.modified = modificationTimes(&*shader);
+
+                Some(shader)
+            }
+            Err(err) => {
+                log::error!("Failed to reload shader {:?}, keeping the old one: {}", shader.name(), err);
+
+                None
+            }
+        }
+    }
+}
+
+fn modificationTimes<S: Reloadable + ?Sized>(shader: &S) -> HashMap<PathBuf, Option<SystemTime>> {
+    shader
+        .dependencies()
+        .into_iter()
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+
+            (path, modified)
+        })
+        .collect()
+}
+
+/// Shaders reloaded by one `ShaderWatcher::poll`.
+#[derive(Default)]
+pub struct ReloadedShaders {
+    pub shaders: Vec<Arc<Shader>>,
+    pub computeShaders: Vec<Arc<ComputeShader>>,
+}
+
+/// Reloads shaders when their source files or any of their includes change on disk.
+///
+/// Files are polled rather than watched, so call `poll` regularly from the render loop, between frames;
+/// it only touches the file system once per interval. Shaders and caches are held weakly, dropping them
+/// stops watching.
+///
+/// Only pipelines in the caches given to `rebuildPipelinesIn` are rebuilt. Pipelines created outside of them,
+/// and compute pipelines, which are never cached, keep using the old modules until their owner replaces them;
+/// `isOutdated` tells when that's due and `rebuild` creates the replacement.
+pub struct ShaderWatcher {
+    interval: Duration,
+    lastPoll: Instant,
+    shaders: Vec<WatchedShader<Shader>>,
+    computeShaders: Vec<WatchedShader<ComputeShader>>,
+    caches: Vec<Weak<PipelineCache>>,
+}
+impl ShaderWatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            lastPoll: Instant::now(),
+            shaders: Vec::new(),
+            computeShaders: Vec::new(),
+            caches: Vec::new(),
+        }
+    }
+    /// Shaders that weren't loaded from files have nothing to watch and are ignored.
+    pub fn watch(&mut self, shader: &Arc<Shader>) -> &mut Self {
+        if !shader.dependencies().is_empty() {
+            self.shaders.push(WatchedShader::new(shader));
+        }
+
+        self
+    }
+    /// Compute shaders that weren't loaded from files have nothing to watch and are ignored.
+    pub fn watchCompute(&mut self, shader: &Arc<ComputeShader>) -> &mut Self {
+        if !shader.dependencies().is_empty() {
+            self.computeShaders.push(WatchedShader::new(shader));
+        }
+
+        self
+    }
+    /// Pipelines in `cache` get rebuilt whenever one of their shaders is reloaded.
+    pub fn rebuildPipelinesIn(&mut self, cache: &Arc<PipelineCache>) -> &mut Self {
+        self.caches.push(Arc::downgrade(cache));
+
+        self
+    }
+    /// Reloads every shader with modified files, returning the ones that were reloaded successfully.
+    ///
+    /// Compilation errors are logged and the shader keeps its previous modules.
+    pub fn poll(&mut self) -> ReloadedShaders {
+        if self.lastPoll.elapsed() < self.interval {
+            return ReloadedShaders::default();
+        }
+        self.lastPoll = Instant::now();
+
+        self.shaders.retain(|watched| watched.shader.strong_count() > 0);
+        self.computeShaders.retain(|watched| watched.shader.strong_count() > 0);
+        self.caches.retain(|cache| cache.strong_count() > 0);
+
+        let reloaded = ReloadedShaders {
+            shaders: self.shaders.iter_mut().filter_map(WatchedShader::poll).collect(),
+            computeShaders: self.computeShaders.iter_mut().filter_map(WatchedShader::poll).collect(),
+        };
+
+        for cache in self.caches.iter().filter_map(Weak::upgrade) {
+            for shader in &reloaded.shaders {
+                cache.rebuild(shader);
+            }
+        }
+
+        reloaded
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use ash::vk;
+use naga::{
+    back::spv,
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+    SourceLocation,
+};
+
+use super::{
+    diagnostics::{self, Diagnostic, Severity},
+    ShaderCreateError, ShaderStage,
+};
+
+fn nagaStage(stage: ShaderStage) -> Option<naga::ShaderStage> {
+    match stage {
+        ShaderStage::Vertex => Some(naga::ShaderStage::Vertex),
+        ShaderStage::Fragment => Some(naga::ShaderStage::Fragment),
+        ShaderStage::Compute => Some(naga::ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+/// What WGSL shaders may use on a device with `features`; without a device, e.g. when compiling offline,
+/// that's everything naga supports.
+pub(crate) fn capabilities(features: Option<&vk::PhysicalDeviceFeatures>) -> Capabilities {
+    let features = match features {
+        Some(features) => features,
+        None => return Capabilities::all(),
+    };
+
+    let mut capabilities = Capabilities::PUSH_CONSTANT;
+    capabilities.set(Capabilities::FLOAT64, features.shader_float64 == vk::TRUE);
+    capabilities.set(Capabilities::CLIP_DISTANCE, features.shader_clip_distance == vk::TRUE);
+    capabilities.set(Capabilities::CULL_DISTANCE, features.shader_cull_distance == vk::TRUE);
+    // SPIR-V's PrimitiveId in fragment shaders needs one of these.
+    capabilities.set(
+        Capabilities::PRIMITIVE_INDEX,
+        features.geometry_shader == vk::TRUE || features.tessellation_shader == vk::TRUE,
+    );
+    capabilities.set(
+        Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+        features.shader_storage_image_extended_formats == vk::TRUE,
+    );
+
+    capabilities
+}
+
+/// Translates WGSL to SPIR-V through naga, validated against `capabilities`.
+///
+/// Parse and validation errors come back as `CompilationError` diagnostics, located in the WGSL source.
+/// WGSL has neither includes nor a preprocessor, so include paths and defines don't apply.
+pub(crate) fn translate(
+    sourceName: &str,
+    sourcePath: Option<PathBuf>,
+    text: &str,
+    entryPoint: &str,
+    stage: ShaderStage,
+    capabilities: Capabilities,
+) -> Result<Vec<u8>, ShaderCreateError> {
+    let fail = |location: Option<SourceLocation>, message: String| {
+        let diagnostics = vec![Diagnostic {
+            file: sourceName.to_owned(),
+            path: sourcePath.clone(),
+            line: location.map(|location| location.line_number),
+            column: location.map(|location| location.line_position),
+            severity: Severity::Error,
+            message,
+        }];
+
+        diagnostics::report(sourceName, &diagnostics);
+
+        ShaderCreateError::CompilationError(sourceName.to_owned(), diagnostics)
+    };
+
+    let nagaStage = nagaStage(stage).ok_or(ShaderCreateError::UnsupportedWgslStage(stage))?;
+
+    let module = wgsl::parse_str(text).map_err(|err| fail(err.location(text), err.message().to_owned()))?;
+
+    let info = Validator::new(ValidationFlags::all(), capabilities)
+        .validate(&module)
+        .map_err(|err| fail(err.location(text), err.as_inner().to_string()))?;
+
+    if !module
+        .entry_points
+        .iter()
+        .any(|entry| entry.name == entryPoint && entry.stage == nagaStage)
+    {
+        return Err(fail(
+            None,
+            format!("no {:?} entry point named {:?}", stage, entryPoint),
+        ));
+    }
+
+    let pipelineOptions = spv::PipelineOptions {
+        shader_stage: nagaStage,
+        entry_point: entryPoint.to_owned(),
+    };
+
+    // The default options only write debug names in debug builds, but reflection needs them to look up
+    // interface variables, bindings and block members.
+    let options = spv::Options {
+        flags: spv::WriterFlags::DEBUG
+            | spv::WriterFlags::ADJUST_COORDINATE_SPACE
+            | spv::WriterFlags::LABEL_VARYINGS
+            | spv::WriterFlags::CLAMP_FRAG_DEPTH,
+        ..spv::Options::default()
+    };
+
+    let words = spv::write_vec(&module, &info, &options, Some(&pipelineOptions))
+        .map_err(|err| fail(None, err.to_string()))?;
+
+    Ok(words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::shader::reflection::reflectEntryPoint;
+
+    const UNDEFINED_IDENTIFIER: &str = "@vertex
+fn main() -> @builtin(position) vec4<f32> {
+    return undefinedPosition;
+}
+";
+
+    const FLOAT64: &str = "@compute @workgroup_size(1)
+fn main() {
+    var value: f64 = f64(1.0);
+}
+";
+
+    const NAMED: &str = "struct Constants {
+    scale: f32,
+    offset: vec2<f32>,
+}
+
+struct Output {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+var<push_constant> constants: Constants;
+@group(0) @binding(0) var<uniform> tint: vec4<f32>;
+
+@vertex
+fn main(@location(0) position: vec2<f32>) -> Output {
+    var output: Output;
+    output.position = vec4<f32>(position * constants.scale + constants.offset, 0.0, 1.0);
+    output.color = tint;
+    output.uv = position;
+    return output;
+}
+";
+
+    #[test]
+    fn translationKeepsNames() {
+        let spirv = translate("test.wgsl", None, NAMED, "main", ShaderStage::Vertex, Capabilities::all()).unwrap();
+        let reflection = reflectEntryPoint(&spirv, "main", ShaderStage::Vertex).unwrap();
+
+        let mut outputs: Vec<_> = reflection.outputs().keys().map(String::as_str).collect();
+        outputs.sort_unstable();
+        assert_eq!(outputs, ["color", "uv"]);
+
+        assert!(reflection.bindingNamed("tint").is_some());
+
+        let pushConstants = reflection.pushConstants().unwrap();
+        let members: Vec<_> = pushConstants.members().iter().map(|member| member.name()).collect();
+        assert_eq!(members, ["scale", "offset"]);
+    }
+
+    #[test]
+    fn translationErrorHasLocation() {
+        let err = translate(
+            "test.wgsl",
+            None,
+            UNDEFINED_IDENTIFIER,
+            "main",
+            ShaderStage::Vertex,
+            Capabilities::all(),
+        )
+        .unwrap_err();
+
+        let diagnostics = match err {
+            ShaderCreateError::CompilationError(_, diagnostics) => diagnostics,
+            err => panic!("expected a compilation error, got {:?}", err),
+        };
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "test.wgsl");
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(12));
+    }
+
+    #[test]
+    fn validatesAgainstDeviceFeatures() {
+        let features = vk::PhysicalDeviceFeatures::default();
+        let float64 = vk::PhysicalDeviceFeatures { shader_float64: vk::TRUE, ..features };
+
+        let translateWith = |features| {
+            translate("test.wgsl", None, FLOAT64, "main", ShaderStage::Compute, capabilities(Some(&features)))
+        };
+
+        assert!(matches!(translateWith(features), Err(ShaderCreateError::CompilationError(..))));
+        assert!(translateWith(float64).is_ok());
+    }
+}
@@ -42,4 +42,14 @@ impl From<vk::Format> for DepthStencilFormat {
         _=> todo!()
      }
  }   
-}
\ No newline at end of file
+}
+
+pub(crate) fn hasStencil(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::S8_UINT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}